@@ -1,6 +1,11 @@
+use crate::djed_dom::backend::{Backend, DomBackend, TimeoutHandle};
+use futures::future::{AbortHandle, Abortable};
+use slab::Slab;
 use std::rc::Rc;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::fmt;
+use std::future::Future;
+use std::time::Duration;
 pub enum Callback<IN> {
     /// A callback which can be called multiple times
     Callback(Rc<dyn Fn(IN)>),
@@ -26,6 +31,7 @@ impl<IN> Clone for Callback<IN> {
     }
 }
 
+/// Callbacks are compared by identity: a callback is only equal to its clones.
 #[allow(clippy::vtable_address_comparisons)]
 impl<IN> PartialEq for Callback<IN> {
     fn eq(&self, other: &Callback<IN>) -> bool {
@@ -100,4 +106,214 @@ impl<IN: 'static> Callback<IN> {
         };
         Callback::from(func)
     }
+
+    /// Changes the input type of the callback to another like `reform`, but drops the
+    /// inputs for which `func` returns `None`.
+    pub fn filter_reform<F, T>(&self, func: F) -> Callback<T>
+    where
+        F: Fn(T) -> Option<IN> + 'static,
+    {
+        let this = self.clone();
+        let func = move |input| {
+            if let Some(output) = func(input) {
+                this.emit(output);
+            }
+        };
+        Callback::from(func)
+    }
+
+    /// Creates a callback which calls this one with its last input, once it wasn't called
+    /// for `duration`. The pending call is cancelled when the returned callback and its
+    /// clones are dropped.
+    ///
+    /// The timeout is set on the [`Backend`], so natively it is only called when the time of
+    /// the in-memory backend is advanced.
+    pub fn debounce(&self, duration: Duration) -> Callback<IN> {
+        let this = self.clone();
+        let pending: RefCell<Option<TimeoutHandle>> = RefCell::new(None);
+        let func = move |input| {
+            let this = this.clone();
+            let emit = Box::new(move || this.emit(input));
+            // Replacing the timeout cancels the previous call
+            let _previous = pending.replace(Some(Backend::set_timeout(duration, emit)));
+        };
+        Callback::from(func)
+    }
+
+    /// Creates a callback which calls this one at most once per `duration`. The inputs it
+    /// receives while it waits are dropped. The timer is cancelled when the returned
+    /// callback and its clones are dropped.
+    ///
+    /// The timer is set on the [`Backend`], like the one of [`debounce`](Self::debounce).
+    pub fn throttle(&self, duration: Duration) -> Callback<IN> {
+        let this = self.clone();
+        let waiting = Rc::new(Cell::new(false));
+        let timer: RefCell<Option<TimeoutHandle>> = RefCell::new(None);
+        let func = move |input| {
+            if waiting.replace(true) {
+                return;
+            }
+            // The timer doesn't keep the flag alive, which is dropped with the callback
+            let flag = Rc::downgrade(&waiting);
+            let reset = Box::new(move || {
+                if let Some(flag) = flag.upgrade() {
+                    flag.set(false);
+                }
+            });
+            let _previous = timer.replace(Some(Backend::set_timeout(duration, reset)));
+            this.emit(input);
+        };
+        Callback::from(func)
+    }
+
+    /// Changes the input type of the callback to another with an asynchronous function.
+    /// The future returned by `func` is spawned on the [`Backend`], and its output is passed
+    /// to this callback once it resolves. The futures which are still running are aborted
+    /// when the returned callback and its clones are dropped, like when the component which
+    /// rendered it is destroyed, so their outputs are dropped.
+    pub fn map_async<F, FU, T>(&self, func: F) -> Callback<T>
+    where
+        F: Fn(T) -> FU + 'static,
+        FU: Future<Output = IN> + 'static,
+    {
+        let this = self.clone();
+        let running = Rc::new(AbortOnDrop::default());
+        let func = move |input| {
+            let this = this.clone();
+            let (future, key) = running.abortable(func(input));
+            let running = Rc::downgrade(&running);
+            Backend::spawn_local(Box::pin(async move {
+                if let Ok(output) = future.await {
+                    if let Some(running) = running.upgrade() {
+                        running.remove(key);
+                    }
+                    this.emit(output);
+                }
+            }));
+        };
+        Callback::from(func)
+    }
+}
+
+/// Aborts the futures it made abortable when it is dropped.
+#[derive(Debug, Default)]
+pub(crate) struct AbortOnDrop {
+    handles: RefCell<Slab<AbortHandle>>,
+}
+
+impl AbortOnDrop {
+    /// Makes the future abortable, and returns it with the key to [`remove`](Self::remove)
+    /// its handle once it completes.
+    pub(crate) fn abortable<F: Future>(&self, future: F) -> (Abortable<F>, usize) {
+        let (handle, registration) = AbortHandle::new_pair();
+        let key = self.handles.borrow_mut().insert(handle);
+        (Abortable::new(future, registration), key)
+    }
+
+    /// Forgets the handle of a completed future.
+    pub(crate) fn remove(&self, key: usize) {
+        self.handles.borrow_mut().remove(key);
+    }
+
+    /// Aborts all the futures which are still running.
+    pub(crate) fn abort_all(&self) {
+        let handles = std::mem::take(&mut *self.handles.borrow_mut());
+        for (_, handle) in handles {
+            handle.abort();
+        }
+    }
+}
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.abort_all();
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::djed_dom::backend::MemoryBackend;
+    use futures::channel::oneshot;
+
+    fn collect() -> (Callback<u32>, Rc<RefCell<Vec<u32>>>) {
+        let outputs = Rc::new(RefCell::new(Vec::new()));
+        let push = outputs.clone();
+        (
+            Callback::from(move |output| push.borrow_mut().push(output)),
+            outputs,
+        )
+    }
+
+    #[test]
+    fn reform_maps_the_inputs() {
+        let (callback, outputs) = collect();
+        let reformed = callback.reform(|input: &str| input.len() as u32);
+        reformed.emit("one");
+        reformed.emit("three");
+        assert_eq!(*outputs.borrow(), vec![3, 5]);
+    }
+
+    #[test]
+    fn filter_reform_drops_the_filtered_inputs() {
+        let (callback, outputs) = collect();
+        let parsed = callback.filter_reform(|input: &str| input.parse().ok());
+        parsed.emit("1");
+        parsed.emit("one");
+        parsed.emit("2");
+        assert_eq!(*outputs.borrow(), vec![1, 2]);
+    }
+
+    #[test]
+    fn debounce_calls_with_the_last_input() {
+        let (callback, outputs) = collect();
+        let debounced = callback.debounce(Duration::from_millis(100));
+        debounced.emit(1);
+        MemoryBackend::advance_time(Duration::from_millis(50));
+        debounced.emit(2);
+        MemoryBackend::advance_time(Duration::from_millis(99));
+        assert!(outputs.borrow().is_empty());
+        MemoryBackend::advance_time(Duration::from_millis(1));
+        assert_eq!(*outputs.borrow(), vec![2]);
+
+        debounced.emit(3);
+        drop(debounced);
+        MemoryBackend::advance_time(Duration::from_millis(100));
+        assert_eq!(*outputs.borrow(), vec![2]);
+    }
+
+    #[test]
+    fn throttle_drops_the_inputs_while_it_waits() {
+        let (callback, outputs) = collect();
+        let throttled = callback.throttle(Duration::from_millis(100));
+        throttled.emit(1);
+        throttled.emit(2);
+        MemoryBackend::advance_time(Duration::from_millis(99));
+        throttled.emit(3);
+        MemoryBackend::advance_time(Duration::from_millis(1));
+        throttled.emit(4);
+        throttled.emit(5);
+        assert_eq!(*outputs.borrow(), vec![1, 4]);
+    }
+
+    #[test]
+    fn map_async_drops_the_outputs_once_dropped() {
+        let (callback, outputs) = collect();
+        let mapped = callback.map_async(|receiver: oneshot::Receiver<u32>| async move {
+            receiver.await.unwrap_or_default()
+        });
+        let (first, receiver) = oneshot::channel();
+        mapped.emit(receiver);
+        let (second, receiver) = oneshot::channel();
+        mapped.emit(receiver);
+
+        first.send(1).unwrap();
+        MemoryBackend::run_futures();
+        assert_eq!(*outputs.borrow(), vec![1]);
+
+        drop(mapped);
+        let _ = second.send(2);
+        MemoryBackend::run_futures();
+        assert_eq!(*outputs.borrow(), vec![1]);
+    }
 }
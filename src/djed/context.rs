@@ -0,0 +1,385 @@
+use super::{AnyScope, Children, Component, ComponentLink, Html, Props, Scope, ShouldRender};
+use crate::callback::Callback;
+use crate::djed_dom::VList;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::{Rc, Weak};
+
+/// Properties of a [`ContextProvider`].
+#[derive(Clone, PartialEq)]
+pub struct ContextProviderProps<T: Clone + PartialEq> {
+    /// The value provided to the descendants.
+    pub context: T,
+    /// The children which can consume the value.
+    pub children: Children,
+}
+
+impl<T: Clone + PartialEq> fmt::Debug for ContextProviderProps<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ContextProviderProps<_>")
+    }
+}
+
+impl<T: Clone + PartialEq> Props for ContextProviderProps<T> {
+    type Builder = ContextProviderPropsBuilder<T>;
+
+    fn builder() -> Self::Builder {
+        ContextProviderPropsBuilder {
+            context: None,
+            children: Children::default(),
+        }
+    }
+}
+
+/// Builder of [`ContextProviderProps`].
+#[doc(hidden)]
+pub struct ContextProviderPropsBuilder<T> {
+    context: Option<T>,
+    children: Children,
+}
+
+impl<T> fmt::Debug for ContextProviderPropsBuilder<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ContextProviderPropsBuilder<_>")
+    }
+}
+
+impl<T: Clone + PartialEq> ContextProviderPropsBuilder<T> {
+    /// Sets the provided value.
+    pub fn context(mut self, context: impl Into<T>) -> Self {
+        self.context = Some(context.into());
+        self
+    }
+
+    /// Sets the children.
+    pub fn children(mut self, children: Children) -> Self {
+        self.children = children;
+        self
+    }
+
+    /// Builds the properties.
+    pub fn build(self) -> ContextProviderProps<T> {
+        ContextProviderProps {
+            context: self
+                .context
+                .expect("the context of a ContextProvider is required"),
+            children: self.children,
+        }
+    }
+}
+
+/// A component which provides a value of type `T` to all its descendants.
+///
+/// Descendants read the value of the nearest provider with [`Scope::context`], or with
+/// [`use_context`](crate::djed::functional::use_context) in function components. They are
+/// notified when the provided value changes.
+pub struct ContextProvider<T: Clone + PartialEq + 'static> {
+    context: T,
+    children: Children,
+    consumers: RefCell<Vec<Weak<Callback<T>>>>,
+}
+
+impl<T: Clone + PartialEq + 'static> fmt::Debug for ContextProvider<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ContextProvider<_>")
+    }
+}
+
+impl<T: Clone + PartialEq + 'static> ContextProvider<T> {
+    /// Subscribes the `callback` to changes of the provided value.
+    fn subscribe(&self, callback: Callback<T>) -> ContextHandle<T> {
+        let callback = Rc::new(callback);
+        self.consumers.borrow_mut().push(Rc::downgrade(&callback));
+        ContextHandle {
+            _callback: callback,
+        }
+    }
+
+    /// Notifies the consumers which are still subscribed of the provided value.
+    fn notify_consumers(&self) {
+        let consumers: Vec<Rc<Callback<T>>> = {
+            let mut consumers = self.consumers.borrow_mut();
+            consumers.retain(|consumer| consumer.strong_count() > 0);
+            consumers.iter().filter_map(Weak::upgrade).collect()
+        };
+        for consumer in consumers {
+            consumer.emit(self.context.clone());
+        }
+    }
+}
+
+impl<T: Clone + PartialEq + 'static> Component for ContextProvider<T> {
+    type State = ();
+    type Props = ContextProviderProps<T>;
+
+    fn create(props: Self::Props, _link: ComponentLink<Self>) -> Self {
+        ContextProvider {
+            context: props.context,
+            children: props.children,
+            consumers: RefCell::new(Vec::new()),
+        }
+    }
+
+    fn update(&mut self, _state: Self::State) -> ShouldRender {
+        false
+    }
+
+    fn change(&mut self, props: Self::Props) -> ShouldRender {
+        let context_changed = self.context != props.context;
+        self.context = props.context;
+        self.children = props.children;
+        if context_changed {
+            self.notify_consumers();
+        }
+        true
+    }
+
+    fn view(&self) -> Html {
+        VList::new_with_children(self.children.iter().collect(), None).into()
+    }
+}
+
+/// A subscription to the value of a [`ContextProvider`]. The consumer stops being notified of
+/// changes when it is dropped.
+pub struct ContextHandle<T> {
+    _callback: Rc<Callback<T>>,
+}
+
+impl<T> fmt::Debug for ContextHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ContextHandle<_>")
+    }
+}
+
+impl AnyScope {
+    /// Returns the value of the nearest ancestor [`ContextProvider`] of type `T`, along with a
+    /// handle which keeps `callback` subscribed to its changes.
+    pub fn context<T: Clone + PartialEq + 'static>(
+        &self,
+        callback: Callback<T>,
+    ) -> Option<(T, ContextHandle<T>)> {
+        let scope = self.find_parent_scope::<ContextProvider<T>>()?;
+        let provider = scope.get_component()?;
+        Some((provider.context.clone(), provider.subscribe(callback)))
+    }
+}
+
+impl<COMP: Component> Scope<COMP> {
+    /// Returns the value of the nearest ancestor [`ContextProvider`] of type `T`, along with a
+    /// handle which keeps `callback` subscribed to its changes.
+    pub fn context<T: Clone + PartialEq + 'static>(
+        &self,
+        callback: Callback<T>,
+    ) -> Option<(T, ContextHandle<T>)> {
+        AnyScope::from(self.clone()).context(callback)
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::djed::NodeRef;
+    use crate::djed_dom::{VComp, VTag, VText};
+    use crate::testing::TestRenderer;
+
+    thread_local! {
+        static NOTIFIED: RefCell<Vec<String>> = RefCell::new(Vec::new());
+    }
+
+    fn take_notified() -> Vec<String> {
+        NOTIFIED.with(|notified| notified.borrow_mut().drain(..).collect())
+    }
+
+    #[derive(Clone, PartialEq)]
+    struct ThemeProps {
+        name: &'static str,
+        subscribed: bool,
+    }
+
+    impl Props for ThemeProps {
+        type Builder = ();
+
+        fn builder() {}
+    }
+
+    /// Shows the theme of the nearest provider, and logs its changes while it is subscribed.
+    struct Theme {
+        name: &'static str,
+        theme: Option<String>,
+        handle: Option<ContextHandle<String>>,
+    }
+
+    impl Component for Theme {
+        type State = String;
+        type Props = ThemeProps;
+
+        fn create(props: ThemeProps, link: ComponentLink<Self>) -> Self {
+            let name = props.name;
+            let changed = link.callback(|theme| theme);
+            let callback = Callback::from(move |theme: String| {
+                NOTIFIED.with(|notified| notified.borrow_mut().push(format!("{} {}", name, theme)));
+                changed.emit(theme);
+            });
+            let (theme, handle) = match link.context(callback) {
+                Some((theme, handle)) => (Some(theme), Some(handle)),
+                None => (None, None),
+            };
+            Theme {
+                name,
+                theme,
+                handle,
+            }
+        }
+
+        fn update(&mut self, theme: String) -> ShouldRender {
+            self.theme = Some(theme);
+            true
+        }
+
+        fn change(&mut self, props: ThemeProps) -> ShouldRender {
+            if !props.subscribed {
+                self.handle = None;
+            }
+            false
+        }
+
+        fn view(&self) -> Html {
+            let theme = self.theme.as_deref().unwrap_or("none");
+            let mut span = VTag::new("span");
+            span.add_child(VText::new(format!("{} {}", self.name, theme)).into());
+            span.into()
+        }
+    }
+
+    enum Msg {
+        Outer(&'static str),
+        Inner(&'static str),
+        Unsubscribe,
+        HideConsumers,
+    }
+
+    /// Nests two providers, with a consumer in each.
+    struct Themes {
+        outer: &'static str,
+        inner: &'static str,
+        subscribed: bool,
+        consumers: bool,
+    }
+
+    fn provider(context: &str, children: Vec<Html>) -> Html {
+        let props = ContextProviderProps {
+            context: context.to_string(),
+            children: Children::new(children),
+        };
+        VComp::new::<ContextProvider<String>>(props, NodeRef::default(), None).into()
+    }
+
+    impl Themes {
+        fn consumer(&self, name: &'static str) -> Vec<Html> {
+            if !self.consumers {
+                return Vec::new();
+            }
+            let props = ThemeProps {
+                name,
+                subscribed: self.subscribed,
+            };
+            vec![VComp::new::<Theme>(props, NodeRef::default(), None).into()]
+        }
+    }
+
+    impl Component for Themes {
+        type State = Msg;
+        type Props = ();
+
+        fn create(_: (), _: ComponentLink<Self>) -> Self {
+            Themes {
+                outer: "dark",
+                inner: "light",
+                subscribed: true,
+                consumers: true,
+            }
+        }
+
+        fn update(&mut self, msg: Msg) -> ShouldRender {
+            match msg {
+                Msg::Outer(theme) => self.outer = theme,
+                Msg::Inner(theme) => self.inner = theme,
+                Msg::Unsubscribe => self.subscribed = false,
+                Msg::HideConsumers => self.consumers = false,
+            }
+            true
+        }
+
+        fn change(&mut self, _: ()) -> ShouldRender {
+            false
+        }
+
+        fn view(&self) -> Html {
+            let mut children = vec![provider(self.inner, self.consumer("inner"))];
+            children.extend(self.consumer("outer"));
+            provider(self.outer, children)
+        }
+    }
+
+    fn send(renderer: &TestRenderer<Themes>, msg: Msg) {
+        renderer.link().send_message(msg);
+        renderer.flush();
+    }
+
+    #[test]
+    fn consumers_read_the_nearest_provider() {
+        let renderer = TestRenderer::<Themes>::mount();
+        assert_eq!(
+            renderer.html(),
+            "<span>inner light</span><span>outer dark</span>"
+        );
+
+        let props = ThemeProps {
+            name: "alone",
+            subscribed: true,
+        };
+        let alone = TestRenderer::<Theme>::mount_with_props(props);
+        assert_eq!(alone.html(), "<span>alone none</span>");
+    }
+
+    #[test]
+    fn consumers_render_the_changed_value() {
+        let renderer = TestRenderer::<Themes>::mount();
+        send(&renderer, Msg::Outer("dim"));
+        assert_eq!(take_notified(), vec!["outer dim"]);
+        assert_eq!(
+            renderer.html(),
+            "<span>inner light</span><span>outer dim</span>"
+        );
+
+        send(&renderer, Msg::Inner("bright"));
+        assert_eq!(take_notified(), vec!["inner bright"]);
+        assert_eq!(
+            renderer.html(),
+            "<span>inner bright</span><span>outer dim</span>"
+        );
+    }
+
+    #[test]
+    fn dropped_handles_unsubscribe() {
+        let renderer = TestRenderer::<Themes>::mount();
+        send(&renderer, Msg::Unsubscribe);
+        send(&renderer, Msg::Outer("dim"));
+        send(&renderer, Msg::Inner("bright"));
+        assert!(take_notified().is_empty());
+        assert_eq!(
+            renderer.html(),
+            "<span>inner light</span><span>outer dark</span>"
+        );
+    }
+
+    #[test]
+    fn destroyed_consumers_unsubscribe() {
+        let renderer = TestRenderer::<Themes>::mount();
+        send(&renderer, Msg::HideConsumers);
+        assert_eq!(renderer.html(), "");
+        send(&renderer, Msg::Outer("dim"));
+        send(&renderer, Msg::Inner("bright"));
+        assert!(take_notified().is_empty());
+    }
+}
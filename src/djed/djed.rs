@@ -3,14 +3,16 @@ use crate::djed_dom::{VChild, VNode};
 use std::cell::RefCell;
 use std::fmt;
 use std::rc::Rc;
-use wasm_bindgen::JsValue;
-use web_sys::Node;
-use super::Scope;
+use crate::djed_dom::backend::Node;
+use super::{RenderError, Scope};
 
 
 /// This type indicates that component should be rendered again.
 pub type ShouldRender = bool;
 
+/// Compares the properties of a memoized component, see [`Component::PROPS_EQUAL`].
+pub type PropsEqual<COMP> = fn(&<COMP as Component>::Props, &<COMP as Component>::Props) -> bool;
+
 /// Components are the basic building blocks of the UI in a djed app. Each Component
 /// chooses how to display itself using received props and self-managed state.
 /// Components can be dynamic and interactive by declaring messages that are
@@ -79,11 +81,42 @@ pub trait Component: Sized + 'static {
     /// Components which don't have properties should always return false.
     fn change(&mut self, _props: Self::Props) -> ShouldRender;
 
+    /// Memoized components keep a copy of their properties, which is compared with the new
+    /// ones by this function before they are changed. When it returns `true`, neither
+    /// `change` nor `view` are called and the component keeps its view. Components with
+    /// `PartialEq` properties are memoized with:
+    /// ```
+    ///# use djed::djed::{Html, Component, ComponentLink, PropsEqual, ShouldRender};
+    ///# struct Model;
+    ///# impl Component for Model {
+    ///#     type State = ();
+    ///#     type Props = ();
+    ///#     fn create(props: Self::Props,link: ComponentLink<Self>) -> Self {unimplemented!()}
+    ///#     fn update(&mut self,msg: Self::State) -> bool {unimplemented!()}
+    ///#     fn change(&mut self, _props: Self::Props) -> ShouldRender { unimplemented!() }
+    ///#     fn view(&self) -> Html {unimplemented!()}
+    /// const PROPS_EQUAL: Option<PropsEqual<Self>> = Some(PartialEq::eq);
+    ///# }
+    /// ```
+    /// Components aren't memoized by default, so that `change` is always called.
+    const PROPS_EQUAL: Option<PropsEqual<Self>> = None;
+
     /// Components define their visual layout using a JSX-style syntax through the use of the
     /// `html!` procedural macro. The full guide to using the macro can be found in [djed's
     /// documentation](https://djed.rs/docs/concepts/html).
     fn view(&self) -> Html;
 
+    /// The fallible variant of `view`, which is the one called to render the component.
+    /// Components which can fail to render override it, and an error it returns is caught by
+    /// the nearest [`ErrorBoundary`](super::ErrorBoundary). Their `view` is then never called
+    /// and can return an empty `Html`.
+    ///
+    /// Returning an error is the way to fail in the browser, where the boundaries can't catch
+    /// panics.
+    fn try_view(&self) -> Result<Html, RenderError> {
+        Ok(self.view())
+    }
+
     /// The `rendered` method is called after each time a Component is rendered but
     /// before the browser updates the page.
     /// ## Examples
@@ -105,6 +138,12 @@ pub trait Component: Sized + 'static {
     /// }
     ///# }
     fn rendered(&mut self, _first_render: bool) {}
+
+    /// The `destroy` method is called before a Component is removed, while its view is still
+    /// attached. Components use it to release the resources they hold outside of their view,
+    /// like global listeners or pending tasks. Their link is destroyed once it is called, see
+    /// [`Scope::is_destroyed`](super::Scope::is_destroyed).
+    fn destroy(&mut self) {}
 }
 
 /// A type which expected as a result of `view` function implementation.
@@ -397,13 +436,31 @@ impl NodeRef {
     }
 
     /// Try converting the node reference into another form
-    pub fn cast<INTO: AsRef<Node> + From<JsValue>>(
+    #[cfg(target_arch = "wasm32")]
+    pub fn cast<INTO: AsRef<web_sys::Node> + From<wasm_bindgen::JsValue>>(
         &self,
     ) -> Option<INTO> {
         let node = self.get();
         node.map(Into::into).map(INTO::from)
     }
 
+    /// Try converting the node reference into another form.
+    ///
+    /// # Panics
+    ///
+    /// Natively the nodes belong to the in-memory DOM and aren't JavaScript objects, so
+    /// casting a reference to a node panics. Use `get` instead.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn cast<INTO: AsRef<web_sys::Node> + From<wasm_bindgen::JsValue>>(
+        &self,
+    ) -> Option<INTO> {
+        assert!(
+            self.get().is_none(),
+            "the nodes of the in-memory DOM can't be cast to web_sys types, use NodeRef::get"
+        );
+        None
+    }
+
     /// Wrap an existing `Node` in a `NodeRef`
     pub(crate) fn new(node: Node) -> Self {
         let node_ref = NodeRef::default();
@@ -0,0 +1,449 @@
+use super::{AnyScope, Children, Component, ComponentLink, Html, Props, Scope, ShouldRender};
+use crate::djed_dom::VList;
+use std::any::{self, Any};
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+
+/// An error returned by a fallible view, see [`Component::try_view`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderError {
+    message: String,
+}
+
+impl RenderError {
+    /// Creates an error described by `message`.
+    pub fn new(message: impl Into<String>) -> Self {
+        RenderError {
+            message: message.into(),
+        }
+    }
+
+    /// Returns the description of the error.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+impl From<String> for RenderError {
+    fn from(message: String) -> Self {
+        RenderError::new(message)
+    }
+}
+
+impl From<&str> for RenderError {
+    fn from(message: &str) -> Self {
+        RenderError::new(message)
+    }
+}
+
+/// The way a component failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComponentErrorKind {
+    /// The fallible view of the component returned an error.
+    View,
+    /// A lifecycle method of the component panicked.
+    Panic,
+}
+
+/// The failure of a component, which is caught by the nearest [`ErrorBoundary`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentError {
+    /// The type name of the component which failed.
+    pub component: &'static str,
+    /// The way the component failed.
+    pub kind: ComponentErrorKind,
+    /// The description of the error, or the message of the panic.
+    pub message: String,
+}
+
+impl ComponentError {
+    pub(crate) fn new<COMP: Component>(kind: ComponentErrorKind, message: String) -> Self {
+        ComponentError {
+            component: any::type_name::<COMP>(),
+            kind,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for ComponentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            ComponentErrorKind::View => write!(f, "the view of {} failed: ", self.component)?,
+            ComponentErrorKind::Panic => write!(f, "{} panicked: ", self.component)?,
+        }
+        f.write_str(&self.message)
+    }
+}
+
+/// Renders the fallback of an [`ErrorBoundary`].
+pub type Fallback = Rc<dyn Fn(&ComponentError) -> Html>;
+
+/// Properties of an [`ErrorBoundary`].
+#[derive(Clone)]
+pub struct ErrorBoundaryProps {
+    /// Renders the fallback shown instead of the children once one of them failed.
+    pub fallback: Fallback,
+    /// The children whose failures are caught.
+    pub children: Children,
+}
+
+impl fmt::Debug for ErrorBoundaryProps {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ErrorBoundaryProps")
+    }
+}
+
+impl Props for ErrorBoundaryProps {
+    type Builder = ErrorBoundaryPropsBuilder;
+
+    fn builder() -> Self::Builder {
+        ErrorBoundaryPropsBuilder {
+            fallback: None,
+            children: Children::default(),
+        }
+    }
+}
+
+/// Builder of [`ErrorBoundaryProps`].
+#[doc(hidden)]
+pub struct ErrorBoundaryPropsBuilder {
+    fallback: Option<Fallback>,
+    children: Children,
+}
+
+impl fmt::Debug for ErrorBoundaryPropsBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ErrorBoundaryPropsBuilder")
+    }
+}
+
+impl ErrorBoundaryPropsBuilder {
+    /// Sets the fallback.
+    pub fn fallback(mut self, fallback: Fallback) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    /// Sets the children.
+    pub fn children(mut self, children: Children) -> Self {
+        self.children = children;
+        self
+    }
+
+    /// Builds the properties.
+    pub fn build(self) -> ErrorBoundaryProps {
+        ErrorBoundaryProps {
+            fallback: self
+                .fallback
+                .expect("the fallback of an ErrorBoundary is required"),
+            children: self.children,
+        }
+    }
+}
+
+/// A component which renders a fallback instead of its children once one of the components
+/// in its subtree failed.
+///
+/// On every target, the boundary catches the errors returned by [`Component::try_view`].
+/// Components which can fail to render should return an error from `try_view` rather than
+/// panic.
+///
+/// Panics in the `create`, `update`, `change`, `view` and `rendered` methods are only caught
+/// natively, like in tests, with [`std::panic::catch_unwind`]. `wasm32-unknown-unknown` is
+/// built with `panic = "abort"`, so in the browser a panicking component still aborts the
+/// whole application and the boundary never renders its fallback.
+///
+/// The children are rendered again when the properties of the boundary change.
+pub struct ErrorBoundary {
+    props: ErrorBoundaryProps,
+    error: Option<ComponentError>,
+}
+
+impl fmt::Debug for ErrorBoundary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ErrorBoundary")
+    }
+}
+
+impl ErrorBoundary {
+    /// Returns the error caught by the boundary, if any.
+    pub fn error(&self) -> Option<&ComponentError> {
+        self.error.as_ref()
+    }
+}
+
+impl Component for ErrorBoundary {
+    type State = ComponentError;
+    type Props = ErrorBoundaryProps;
+
+    fn create(props: Self::Props, _link: ComponentLink<Self>) -> Self {
+        ErrorBoundary { props, error: None }
+    }
+
+    fn update(&mut self, error: Self::State) -> ShouldRender {
+        // Only the first failure is shown, the others are likely caused by it
+        if self.error.is_none() {
+            self.error = Some(error);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn change(&mut self, props: Self::Props) -> ShouldRender {
+        self.props = props;
+        self.error = None;
+        true
+    }
+
+    fn view(&self) -> Html {
+        match &self.error {
+            Some(error) => (self.props.fallback)(error),
+            None => VList::new_with_children(self.props.children.iter().collect(), None).into(),
+        }
+    }
+}
+
+impl<COMP: Component> Scope<COMP> {
+    /// Sends the failure of the component to the nearest [`ErrorBoundary`]. Returns `false` if
+    /// there is no boundary above the component.
+    pub(crate) fn report_error(&self, error: ComponentError) -> bool {
+        match AnyScope::from(self.clone()).find_parent_scope::<ErrorBoundary>() {
+            Some(boundary) => {
+                boundary.send_message(error);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Runs a lifecycle task of a component. A panic is sent to the nearest [`ErrorBoundary`]
+/// of the component returned by `scope`, and resumed if there is none.
+pub(crate) fn catch_panic<COMP, S, F>(scope: S, run: F)
+where
+    COMP: Component,
+    S: FnOnce() -> Option<Scope<COMP>>,
+    F: FnOnce(),
+{
+    if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(run)) {
+        let error =
+            ComponentError::new::<COMP>(ComponentErrorKind::Panic, panic_message(&*payload));
+        match scope() {
+            Some(scope) if scope.report_error(error) => {}
+            _ => panic::resume_unwind(payload),
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Box<Any>".to_string()
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::callback::Callback;
+    use crate::djed::functional::{use_effect, use_state, FunctionComponent, FunctionProvider};
+    use crate::djed::NodeRef;
+    use crate::djed_dom::{VComp, VTag, VText};
+    use crate::testing::TestRenderer;
+    use std::cell::{Cell, RefCell};
+
+    thread_local! {
+        static PANIC: Cell<bool> = Cell::new(false);
+        static SET_COUNT: RefCell<Option<Callback<u32>>> = RefCell::new(None);
+        static CLEANED_UP: Cell<bool> = Cell::new(false);
+    }
+
+    fn text(tag: &str, text: &str) -> Html {
+        let mut tag = VTag::new(tag.to_string());
+        tag.add_child(VText::new(text.to_string()).into());
+        tag.into()
+    }
+
+    /// Shows a counter, and panics in its view once `PANIC` is set.
+    struct FlakyProvider;
+
+    impl FunctionProvider for FlakyProvider {
+        type Props = ();
+
+        fn run(_: &()) -> Html {
+            let (count, set_count) = use_state(|| 0);
+            SET_COUNT.with(|set| *set.borrow_mut() = Some(set_count));
+            use_effect(|| || CLEANED_UP.with(|cleaned_up| cleaned_up.set(true)));
+            if PANIC.with(Cell::get) {
+                panic!("flaky failed");
+            }
+            text("p", &count.to_string())
+        }
+    }
+
+    type Flaky = FunctionComponent<FlakyProvider>;
+
+    /// Wraps a flaky component in a boundary.
+    struct Guarded;
+
+    impl Component for Guarded {
+        type State = ();
+        type Props = ();
+
+        fn create(_: (), _: ComponentLink<Self>) -> Self {
+            Guarded
+        }
+
+        fn update(&mut self, _: ()) -> ShouldRender {
+            false
+        }
+
+        fn change(&mut self, _: ()) -> ShouldRender {
+            false
+        }
+
+        fn view(&self) -> Html {
+            let child = VComp::new::<Flaky>((), NodeRef::default(), None).into();
+            let props = ErrorBoundaryProps {
+                fallback: Rc::new(|error: &ComponentError| text("strong", &error.message)),
+                children: Children::new(vec![child]),
+            };
+            VComp::new::<ErrorBoundary>(props, NodeRef::default(), None).into()
+        }
+    }
+
+    fn set_count(count: u32) {
+        let set_count = SET_COUNT.with(|set| set.borrow().clone()).unwrap();
+        set_count.emit(count);
+    }
+
+    #[test]
+    fn panic_in_view_renders_the_fallback() {
+        PANIC.with(|panic| panic.set(false));
+        let renderer = TestRenderer::<Guarded>::mount();
+        assert_eq!(renderer.html(), "<p>0</p>");
+
+        set_count(1);
+        renderer.flush();
+        assert_eq!(renderer.html(), "<p>1</p>");
+
+        CLEANED_UP.with(|cleaned_up| cleaned_up.set(false));
+        PANIC.with(|panic| panic.set(true));
+        set_count(2);
+        renderer.flush();
+        PANIC.with(|panic| panic.set(false));
+        assert_eq!(renderer.html(), "<strong>flaky failed</strong>");
+        // The hooks survived the panic, so the effect is cleaned up with the component
+        assert!(CLEANED_UP.with(Cell::get));
+    }
+
+    #[derive(Clone)]
+    struct Input(&'static str);
+
+    impl Props for Input {
+        type Builder = ();
+
+        fn builder() {}
+    }
+
+    /// Shows its input, and fails to render it unless it is a number.
+    struct Parser(&'static str);
+
+    impl Component for Parser {
+        type State = ();
+        type Props = Input;
+
+        fn create(input: Input, _: ComponentLink<Self>) -> Self {
+            Parser(input.0)
+        }
+
+        fn update(&mut self, _: ()) -> ShouldRender {
+            false
+        }
+
+        fn change(&mut self, input: Input) -> ShouldRender {
+            self.0 = input.0;
+            true
+        }
+
+        fn view(&self) -> Html {
+            Html::default()
+        }
+
+        fn try_view(&self) -> Result<Html, RenderError> {
+            let number: u32 = self
+                .0
+                .parse()
+                .map_err(|_| format!("not a number: {}", self.0))?;
+            Ok(text("p", &number.to_string()))
+        }
+    }
+
+    /// Parses the input it receives in a boundary.
+    struct Form(&'static str);
+
+    impl Component for Form {
+        type State = &'static str;
+        type Props = ();
+
+        fn create(_: (), _: ComponentLink<Self>) -> Self {
+            Form("1")
+        }
+
+        fn update(&mut self, input: &'static str) -> ShouldRender {
+            self.0 = input;
+            true
+        }
+
+        fn change(&mut self, _: ()) -> ShouldRender {
+            false
+        }
+
+        fn view(&self) -> Html {
+            let child = VComp::new::<Parser>(Input(self.0), NodeRef::default(), None).into();
+            let props = ErrorBoundaryProps {
+                fallback: Rc::new(|error: &ComponentError| {
+                    text("strong", &format!("{:?}: {}", error.kind, error.message))
+                }),
+                children: Children::new(vec![child]),
+            };
+            VComp::new::<ErrorBoundary>(props, NodeRef::default(), None).into()
+        }
+    }
+
+    #[test]
+    fn view_error_renders_the_fallback() {
+        let renderer = TestRenderer::<Form>::mount();
+        assert_eq!(renderer.html(), "<p>1</p>");
+
+        renderer.link().send_message("one");
+        renderer.flush();
+        assert_eq!(renderer.html(), "<strong>View: not a number: one</strong>");
+    }
+
+    #[test]
+    fn new_props_reset_the_boundary() {
+        let renderer = TestRenderer::<Form>::mount();
+        renderer.link().send_message("one");
+        renderer.flush();
+        assert_eq!(renderer.html(), "<strong>View: not a number: one</strong>");
+
+        renderer.link().send_message("2");
+        renderer.flush();
+        assert_eq!(renderer.html(), "<p>2</p>");
+    }
+}
@@ -0,0 +1,218 @@
+use super::use_hook;
+use crate::callback::Callback;
+use crate::djed::ContextHandle;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Returns the current state and a callback which replaces it and renders the component
+/// again. The state is created by `initial_state` on the first render.
+pub fn use_state<T, F>(initial_state: F) -> (Rc<T>, Callback<T>)
+where
+    T: 'static,
+    F: FnOnce() -> T,
+{
+    struct UseState<T> {
+        current: Rc<T>,
+    }
+
+    use_hook(
+        || UseState {
+            current: Rc::new(initial_state()),
+        },
+        |hook: &mut UseState<T>, updater| {
+            let setter = Callback::from(move |new_state: T| {
+                updater.callback(move |hook: &mut UseState<T>| {
+                    hook.current = Rc::new(new_state);
+                    true
+                })
+            });
+            (hook.current.clone(), setter)
+        },
+        None,
+    )
+}
+
+/// Returns the current state and a callback which dispatches actions to the `reducer`. Each
+/// action replaces the state with the one the reducer returns and renders the component
+/// again.
+pub fn use_reducer<Action, State, R>(
+    reducer: R,
+    initial_state: State,
+) -> (Rc<State>, Callback<Action>)
+where
+    Action: 'static,
+    State: 'static,
+    R: Fn(Rc<State>, Action) -> State + 'static,
+{
+    struct UseReducer<State> {
+        current: Rc<State>,
+    }
+
+    let reducer = Rc::new(reducer);
+    use_hook(
+        || UseReducer {
+            current: Rc::new(initial_state),
+        },
+        |hook: &mut UseReducer<State>, updater| {
+            let dispatch = Callback::from(move |action: Action| {
+                let reducer = reducer.clone();
+                updater.callback(move |hook: &mut UseReducer<State>| {
+                    hook.current = Rc::new(reducer(hook.current.clone(), action));
+                    true
+                })
+            });
+            (hook.current.clone(), dispatch)
+        },
+        None,
+    )
+}
+
+/// Returns a mutable reference which persists between renders. Changing its value doesn't
+/// render the component again.
+pub fn use_ref<T, F>(initial_value: F) -> Rc<RefCell<T>>
+where
+    T: 'static,
+    F: FnOnce() -> T,
+{
+    use_hook(
+        || Rc::new(RefCell::new(initial_value())),
+        |hook: &mut Rc<RefCell<T>>, _| hook.clone(),
+        None,
+    )
+}
+
+struct UseEffect<Deps> {
+    deps: Option<Rc<Deps>>,
+    // The deps of the effect which waits for the component to be rendered
+    queued: Option<Rc<Deps>>,
+    destructor: Option<Box<dyn FnOnce()>>,
+}
+
+fn destroy_effect<Deps>(hook: &mut UseEffect<Deps>) {
+    if let Some(destructor) = hook.destructor.take() {
+        destructor()
+    }
+}
+
+/// Runs `callback` after every render of the component. The function it returns is called
+/// to clean up before the next run, and when the component is destroyed.
+pub fn use_effect<F, D>(callback: F)
+where
+    F: FnOnce() -> D + 'static,
+    D: FnOnce() + 'static,
+{
+    use_hook(
+        || UseEffect::<()> {
+            deps: None,
+            queued: None,
+            destructor: None,
+        },
+        |_: &mut UseEffect<()>, updater| {
+            updater.post_render(move |hook: &mut UseEffect<()>| {
+                destroy_effect(hook);
+                hook.destructor = Some(Box::new(callback()));
+                false
+            });
+        },
+        Some(destroy_effect::<()>),
+    )
+}
+
+/// Runs `callback` after the first render of the component, and after the renders where
+/// `deps` changed. The function it returns is called to clean up before the next run, and
+/// when the component is destroyed. When the component is updated several times before it
+/// is rendered, the effect only runs once, with the last `deps`.
+pub fn use_effect_with_deps<F, D, Deps>(callback: F, deps: Deps)
+where
+    F: FnOnce(&Deps) -> D + 'static,
+    D: FnOnce() + 'static,
+    Deps: PartialEq + 'static,
+{
+    let deps = Rc::new(deps);
+    use_hook(
+        || UseEffect::<Deps> {
+            deps: None,
+            queued: None,
+            destructor: None,
+        },
+        |hook: &mut UseEffect<Deps>, updater| {
+            if hook.queued.as_ref().or(hook.deps.as_ref()) != Some(&deps) {
+                hook.queued = Some(deps.clone());
+                updater.post_render(move |hook: &mut UseEffect<Deps>| {
+                    // A later view may have queued the effect again with other deps
+                    if !matches!(&hook.queued, Some(queued) if Rc::ptr_eq(queued, &deps)) {
+                        return false;
+                    }
+                    hook.queued = None;
+                    destroy_effect(hook);
+                    hook.destructor = Some(Box::new(callback(&deps)));
+                    hook.deps = Some(deps);
+                    false
+                });
+            }
+        },
+        Some(destroy_effect::<Deps>),
+    )
+}
+
+/// Returns the value computed by `memo` from `deps`. It is only computed again on the
+/// renders where `deps` changed.
+pub fn use_memo<T, F, Deps>(memo: F, deps: Deps) -> Rc<T>
+where
+    T: 'static,
+    F: FnOnce(&Deps) -> T,
+    Deps: PartialEq + 'static,
+{
+    struct UseMemo<T, Deps> {
+        value: Option<(Rc<T>, Deps)>,
+    }
+
+    use_hook(
+        || UseMemo::<T, Deps> { value: None },
+        |hook: &mut UseMemo<T, Deps>, _| match &hook.value {
+            Some((value, current_deps)) if *current_deps == deps => value.clone(),
+            _ => {
+                let value = Rc::new(memo(&deps));
+                hook.value = Some((value.clone(), deps));
+                value
+            }
+        },
+        None,
+    )
+}
+
+/// Returns the value of the nearest ancestor
+/// [`ContextProvider`](crate::djed::ContextProvider) of type `T`, or `None` if there is none.
+/// The component is rendered again when the provided value changes.
+pub fn use_context<T: Clone + PartialEq + 'static>() -> Option<T> {
+    struct UseContext<T> {
+        initialized: bool,
+        context: Option<(T, ContextHandle<T>)>,
+    }
+
+    use_hook(
+        || UseContext {
+            initialized: false,
+            context: None,
+        },
+        |hook: &mut UseContext<T>, updater| {
+            if !hook.initialized {
+                hook.initialized = true;
+                let callback = {
+                    let updater = updater.clone();
+                    Callback::from(move |value: T| {
+                        updater.callback(move |hook: &mut UseContext<T>| {
+                            if let Some((context, _)) = hook.context.as_mut() {
+                                *context = value;
+                            }
+                            true
+                        })
+                    })
+                };
+                hook.context = updater.scope().context(callback);
+            }
+            hook.context.as_ref().map(|(context, _)| context.clone())
+        },
+        None,
+    )
+}
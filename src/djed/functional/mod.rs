@@ -0,0 +1,504 @@
+//! Function components and the hooks which give them state.
+//!
+//! A function component is a plain function from its properties to its view. It is wrapped
+//! into a [`Component`] by implementing [`FunctionProvider`] for a type and mounting a
+//! [`FunctionComponent`] of it, or with the [`function_component!`](crate::function_component)
+//! macro which does both:
+//!
+//! ```ignore
+//! fn counter(props: &CounterProps) -> Html {
+//!     let (count, set_count) = use_state(|| 0);
+//!     let onclick = {
+//!         let count = count.clone();
+//!         Callback::from(move |_| set_count.emit(*count + 1))
+//!     };
+//!     // ...
+//! }
+//!
+//! function_component!(pub Counter = counter(CounterProps));
+//! ```
+//!
+//! Hooks are stored per mounted component in the order they are called, so they have to be
+//! called unconditionally and in the same order on every render.
+
+mod hooks;
+
+pub use hooks::{
+    use_context, use_effect, use_effect_with_deps, use_memo, use_reducer, use_ref, use_state,
+};
+
+use crate::djed::{AnyScope, Component, ComponentLink, Html, Props, PropsEqual, ShouldRender};
+use std::any::Any;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+thread_local! {
+    static CURRENT_HOOK: RefCell<Option<HookState>> = const { RefCell::new(None) };
+}
+
+/// An update of the hooks of a function component. It returns `true` if the component has to
+/// be rendered again.
+pub type HookMessage = Box<dyn FnOnce() -> ShouldRender>;
+
+type ProcessMessage = Rc<dyn Fn(HookMessage, bool)>;
+
+/// Provides the view of a [`FunctionComponent`].
+pub trait FunctionProvider {
+    /// The properties of the component. The component is rendered again when they change.
+    type Props: Props + PartialEq;
+
+    /// Renders the view of the component. Hooks can be called here.
+    fn run(props: &Self::Props) -> Html;
+}
+
+/// A [`Component`] which renders a [`FunctionProvider`].
+pub struct FunctionComponent<T: FunctionProvider + 'static> {
+    props: T::Props,
+    hook_state: RefCell<Option<HookState>>,
+    post_render: Rc<RefCell<Vec<HookMessage>>>,
+    link: ComponentLink<Self>,
+}
+
+impl<T: FunctionProvider + 'static> fmt::Debug for FunctionComponent<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("FunctionComponent<_>")
+    }
+}
+
+struct HookState {
+    counter: usize,
+    scope: AnyScope,
+    process_message: ProcessMessage,
+    hooks: Vec<Rc<RefCell<dyn Any>>>,
+    destroy_listeners: Vec<Box<dyn FnOnce()>>,
+}
+
+impl<T: FunctionProvider + 'static> Component for FunctionComponent<T> {
+    type State = HookMessage;
+    type Props = T::Props;
+
+    fn create(props: Self::Props, link: ComponentLink<Self>) -> Self {
+        let post_render = Rc::new(RefCell::new(Vec::new()));
+        let process_message: ProcessMessage = {
+            let post_render = post_render.clone();
+            let link = link.clone();
+            Rc::new(move |message, post_render_message| {
+                if post_render_message {
+                    post_render.borrow_mut().push(message);
+                } else {
+                    link.send_message(message);
+                }
+            })
+        };
+
+        FunctionComponent {
+            props,
+            hook_state: RefCell::new(Some(HookState {
+                counter: 0,
+                scope: link.clone().into(),
+                process_message,
+                hooks: Vec::new(),
+                destroy_listeners: Vec::new(),
+            })),
+            post_render,
+            link,
+        }
+    }
+
+    fn update(&mut self, message: Self::State) -> ShouldRender {
+        message()
+    }
+
+    fn change(&mut self, props: Self::Props) -> ShouldRender {
+        if self.props != props {
+            self.props = props;
+            true
+        } else {
+            false
+        }
+    }
+
+    const PROPS_EQUAL: Option<PropsEqual<Self>> = Some(PartialEq::eq);
+
+    fn view(&self) -> Html {
+        // Hooks called by the provider find their slots in the current hook state
+        let mut hook_state = self
+            .hook_state
+            .borrow_mut()
+            .take()
+            .expect("function component rendered recursively");
+        hook_state.counter = 0;
+        let previous = CURRENT_HOOK.with(|current| current.replace(Some(hook_state)));
+        // The hook state is given back even if the provider panics
+        let _guard = RestoreHookState {
+            hook_state: &self.hook_state,
+            previous,
+        };
+
+        T::run(&self.props)
+    }
+
+    fn rendered(&mut self, _first_render: bool) {
+        let messages: Vec<HookMessage> = self.post_render.borrow_mut().drain(..).collect();
+        let mut should_render = false;
+        for message in messages {
+            // Every message runs, even once one of them asked for a render
+            should_render |= message();
+        }
+        if should_render {
+            self.link.send_message(Box::new(|| true) as HookMessage);
+        }
+    }
+}
+
+impl<T: FunctionProvider + 'static> Drop for FunctionComponent<T> {
+    fn drop(&mut self) {
+        if let Some(hook_state) = self.hook_state.borrow_mut().as_mut() {
+            for destroy in hook_state.destroy_listeners.drain(..) {
+                destroy()
+            }
+        }
+    }
+}
+
+/// Gives the hook state of a function component back to it when its view is done, and makes
+/// the hook state of the enclosing view current again.
+struct RestoreHookState<'a> {
+    hook_state: &'a RefCell<Option<HookState>>,
+    previous: Option<HookState>,
+}
+
+impl Drop for RestoreHookState<'_> {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        let hook_state = CURRENT_HOOK.with(|current| current.replace(previous));
+        *self.hook_state.borrow_mut() = hook_state;
+    }
+}
+
+/// A handle to the slot of a hook, which is used to schedule updates of its internal state.
+pub struct HookUpdater {
+    hook: Rc<RefCell<dyn Any>>,
+    scope: AnyScope,
+    process_message: ProcessMessage,
+}
+
+impl fmt::Debug for HookUpdater {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("HookUpdater")
+    }
+}
+
+impl Clone for HookUpdater {
+    fn clone(&self) -> Self {
+        HookUpdater {
+            hook: self.hook.clone(),
+            scope: self.scope.clone(),
+            process_message: self.process_message.clone(),
+        }
+    }
+}
+
+impl HookUpdater {
+    /// Returns the scope of the component which the hook belongs to.
+    pub fn scope(&self) -> &AnyScope {
+        &self.scope
+    }
+
+    /// Schedules an update of the internal state of the hook. The component is rendered
+    /// again if `update` returns `true`.
+    pub fn callback<T: 'static, F>(&self, update: F)
+    where
+        F: FnOnce(&mut T) -> ShouldRender + 'static,
+    {
+        let message = self.message(update);
+        (self.process_message)(message, false);
+    }
+
+    /// Schedules an update of the internal state of the hook, which runs after the
+    /// component has been rendered. The component is rendered again if `update` returns
+    /// `true`.
+    pub fn post_render<T: 'static, F>(&self, update: F)
+    where
+        F: FnOnce(&mut T) -> ShouldRender + 'static,
+    {
+        let message = self.message(update);
+        (self.process_message)(message, true);
+    }
+
+    fn message<T: 'static, F>(&self, update: F) -> HookMessage
+    where
+        F: FnOnce(&mut T) -> ShouldRender + 'static,
+    {
+        let hook = self.hook.clone();
+        Box::new(move || {
+            let mut hook = hook.borrow_mut();
+            let hook = hook.downcast_mut::<T>().expect("incompatible hook type");
+            update(hook)
+        })
+    }
+}
+
+/// Claims the next hook slot of the function component being rendered and returns what
+/// `runner` makes of its internal state.
+///
+/// The internal state is created by `initializer` the first time the component is rendered,
+/// and `destructor` is called with it when the component is destroyed.
+///
+/// # Panics
+/// Panics if it is called outside of a function component, or if the hooks of the component
+/// are not called in the same order as in the previous render.
+pub fn use_hook<InternalHook: 'static, Output, Init, Run>(
+    initializer: Init,
+    runner: Run,
+    destructor: Option<fn(&mut InternalHook)>,
+) -> Output
+where
+    Init: FnOnce() -> InternalHook,
+    Run: FnOnce(&mut InternalHook, HookUpdater) -> Output,
+{
+    let updater = CURRENT_HOOK.with(|current| {
+        let mut current = current.borrow_mut();
+        let hook_state = current
+            .as_mut()
+            .expect("hooks can only be used while rendering a function component");
+
+        let index = hook_state.counter;
+        hook_state.counter += 1;
+        if index == hook_state.hooks.len() {
+            let hook = Rc::new(RefCell::new(initializer()));
+            if let Some(destructor) = destructor {
+                let hook = hook.clone();
+                hook_state
+                    .destroy_listeners
+                    .push(Box::new(move || destructor(&mut hook.borrow_mut())));
+            }
+            hook_state.hooks.push(hook);
+        }
+
+        HookUpdater {
+            hook: hook_state.hooks[index].clone(),
+            scope: hook_state.scope.clone(),
+            process_message: hook_state.process_message.clone(),
+        }
+    });
+
+    let hook = updater.hook.clone();
+    let mut hook = hook.borrow_mut();
+    let hook = hook
+        .downcast_mut::<InternalHook>()
+        .expect("hooks have to be called in the same order on every render");
+    runner(hook, updater)
+}
+
+/// Wraps a function from properties to `Html` into a component.
+///
+/// `function_component!(pub Counter = counter(CounterProps));` declares the `Counter`
+/// component, which renders `counter`. A provider type named after the function is declared
+/// next to it.
+#[macro_export]
+macro_rules! function_component {
+    ($vis:vis $name:ident = $function:ident($props:ty)) => {
+        #[allow(non_camel_case_types)]
+        #[doc(hidden)]
+        $vis struct $function {}
+
+        impl $crate::djed::functional::FunctionProvider for $function {
+            type Props = $props;
+
+            fn run(props: &Self::Props) -> $crate::djed::Html {
+                $function(props)
+            }
+        }
+
+        $vis type $name = $crate::djed::functional::FunctionComponent<$function>;
+    };
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::callback::Callback;
+    use crate::djed::listener::onclick;
+    use crate::djed::NodeRef;
+    use crate::djed_dom::{VComp, VTag, VText};
+    use crate::scheduler::scheduler;
+    use crate::testing::TestRenderer;
+    use web_sys::MouseEvent;
+
+    thread_local! {
+        static LOG: RefCell<Vec<String>> = RefCell::new(Vec::new());
+    }
+
+    fn log(entry: String) {
+        LOG.with(|log| log.borrow_mut().push(entry));
+    }
+
+    fn take_log() -> Vec<String> {
+        LOG.with(|log| log.borrow_mut().drain(..).collect())
+    }
+
+    fn button(text: String, onclick: Callback<MouseEvent>) -> Html {
+        let mut button = VTag::new("button");
+        button.add_listener(Rc::new(onclick::Wrapper::new(onclick)));
+        button.add_child(VText::new(text).into());
+        button.into()
+    }
+
+    /// Counts its clicks with `use_state`, adds them up with `use_reducer` and counts its
+    /// views with `use_ref`.
+    struct Clicks;
+
+    impl FunctionProvider for Clicks {
+        type Props = ();
+
+        fn run(_: &()) -> Html {
+            let (count, set_count) = use_state(|| 0);
+            let (total, add) = use_reducer(|total: Rc<u32>, amount: u32| *total + amount, 10);
+            let views = use_ref(|| 0);
+            *views.borrow_mut() += 1;
+            let count = *count;
+            let onclick = Callback::from(move |_| {
+                set_count.emit(count + 1);
+                add.emit(5);
+            });
+            button(format!("{} {} {}", count, total, views.borrow()), onclick)
+        }
+    }
+
+    #[test]
+    fn state_persists_across_renders() {
+        let renderer = TestRenderer::<FunctionComponent<Clicks>>::mount();
+        assert_eq!(renderer.html(), "<button>0 10 1</button>");
+
+        let button = renderer.find_by_tag("button").remove(0);
+        renderer.click(&button);
+        // Both messages are handled before the render, and each calls the view
+        assert_eq!(renderer.html(), "<button>1 15 3</button>");
+        renderer.click(&button);
+        assert_eq!(renderer.html(), "<button>2 20 5</button>");
+    }
+
+    /// Logs its effects, their cleanups and the memo computed from half its count.
+    struct Effects;
+
+    impl FunctionProvider for Effects {
+        type Props = ();
+
+        fn run(_: &()) -> Html {
+            let (count, add) = use_reducer(|count: Rc<u32>, amount: u32| *count + amount, 0);
+            let count = *count;
+            use_effect(move || {
+                log(format!("effect {}", count));
+                move || log(format!("cleanup {}", count))
+            });
+            use_effect_with_deps(
+                |half: &u32| {
+                    let half = *half;
+                    log(format!("deps effect {}", half));
+                    move || log(format!("deps cleanup {}", half))
+                },
+                count / 2,
+            );
+            let tenfold = use_memo(
+                |half: &u32| {
+                    log(format!("memo {}", half));
+                    half * 10
+                },
+                count / 2,
+            );
+            button(
+                format!("{} {}", count, tenfold),
+                Callback::from(move |_| add.emit(1)),
+            )
+        }
+    }
+
+    /// Shows `Effects` until it receives a message.
+    struct Toggle(bool);
+
+    impl Component for Toggle {
+        type State = ();
+        type Props = ();
+
+        fn create(_: (), _: ComponentLink<Self>) -> Self {
+            Toggle(true)
+        }
+
+        fn update(&mut self, _: ()) -> ShouldRender {
+            self.0 = false;
+            true
+        }
+
+        fn change(&mut self, _: ()) -> ShouldRender {
+            false
+        }
+
+        fn view(&self) -> Html {
+            let mut toggle = VTag::new("div");
+            if self.0 {
+                let effects =
+                    VComp::new::<FunctionComponent<Effects>>((), NodeRef::default(), None);
+                toggle.add_child(effects.into());
+            }
+            toggle.into()
+        }
+    }
+
+    #[test]
+    fn effects_run_after_renders_and_clean_up() {
+        let renderer = TestRenderer::<Toggle>::mount();
+        assert_eq!(take_log(), vec!["memo 0", "effect 0", "deps effect 0"]);
+
+        let button = renderer.find_by_tag("button").remove(0);
+        renderer.click(&button);
+        assert_eq!(renderer.html(), "<div><button>1 0</button></div>");
+        assert_eq!(take_log(), vec!["cleanup 0", "effect 1"]);
+
+        renderer.click(&button);
+        assert_eq!(renderer.html(), "<div><button>2 10</button></div>");
+        assert_eq!(
+            take_log(),
+            vec![
+                "memo 1",
+                "cleanup 1",
+                "effect 2",
+                "deps cleanup 0",
+                "deps effect 1",
+            ]
+        );
+
+        renderer.link().send_message(());
+        renderer.flush();
+        assert_eq!(renderer.html(), "<div></div>");
+        assert_eq!(take_log(), vec!["cleanup 2", "deps cleanup 1"]);
+    }
+
+    #[test]
+    fn effect_with_deps_runs_once_per_render() {
+        let renderer = TestRenderer::<FunctionComponent<Effects>>::mount();
+        take_log();
+        let scheduler = scheduler();
+        scheduler.set_frame_batching(true);
+
+        // Three views run before the component is rendered
+        let button = renderer.find_by_tag("button").remove(0);
+        for _ in 0..3 {
+            button.dispatch_event("click");
+            scheduler.run_until_idle();
+        }
+        assert_eq!(renderer.html(), "<button>0 0</button>");
+        renderer.flush();
+        assert_eq!(renderer.html(), "<button>3 10</button>");
+        let deps_effects: Vec<String> = take_log()
+            .into_iter()
+            .filter(|entry| entry.starts_with("deps") || entry.starts_with("memo"))
+            .collect();
+        assert_eq!(
+            deps_effects,
+            vec!["memo 1", "deps cleanup 0", "deps effect 1"]
+        );
+        scheduler.set_frame_batching(false);
+    }
+}
@@ -1,8 +1,5 @@
-use wasm_bindgen::JsCast;
-use web_sys::{
-    Element, FileList, HtmlInputElement as InputElement, HtmlSelectElement as SelectElement,
-    HtmlTextAreaElement as TextAreaElement,
-};
+use crate::djed_dom::backend::{Backend, DomBackend, Element, WebSysBackend};
+use web_sys::{FileList, HtmlSelectElement as SelectElement};
 
 /// A type representing data from `oninput` event.
 #[derive(Debug)]
@@ -28,53 +25,41 @@ pub enum ChangeData {
     /// to collect your required data such as: `value`, `selected_index`, `selected_indices` or
     /// `selected_values`. You can also iterate throught `selected_options` yourself.
     Select(SelectElement),
+    /// Values of the selected options of a `<select multiple>` in the in-memory DOM, which
+    /// has no `SelectElement`.
+    Values(Vec<String>),
     /// Files
     Files(FileList),
 }
 
+impl ChangeData {
+    /// Returns the value of an `<input>`, a `<textarea>` or a `<select>`, which is the value
+    /// of its first selected option. Returns `None` for files.
+    pub fn value(&self) -> Option<String> {
+        match self {
+            ChangeData::Value(value) => Some(value.clone()),
+            ChangeData::Select(select) => Some(select.value()),
+            ChangeData::Values(values) => Some(values.first().cloned().unwrap_or_default()),
+            ChangeData::Files(_) => None,
+        }
+    }
+
+    /// Returns the values of the selected options of a `<select>`, or the value of an
+    /// `<input>` or a `<textarea>`. Returns an empty list for files.
+    pub fn values(&self) -> Vec<String> {
+        match self {
+            ChangeData::Value(value) => vec![value.clone()],
+            ChangeData::Select(select) => WebSysBackend::selected_values(select),
+            ChangeData::Values(values) => values.clone(),
+            ChangeData::Files(_) => Vec::new(),
+        }
+    }
+}
+
 pub fn oninput_handler(this: &Element) -> InputData {
-    // Normally only InputElement or TextAreaElement can have an oninput event listener. In
-    // practice though any element with `contenteditable=true` may generate such events,
-    // therefore here we fall back to just returning the text content of the node.
-    // See https://developer.mozilla.org/en-US/docs/Web/API/HTMLElement/input_event.
-    let (v1, v2) = {
-        (
-            this.dyn_ref().map(|input: &InputElement| input.value()),
-            this.dyn_ref().map(|input: &TextAreaElement| input.value()),
-        )
-    };
-    let v3 = this.text_content();
-    let value = v1.or(v2).or(v3)
-        .expect("only an InputElement or TextAreaElement or an element with contenteditable=true can have an oninput event listener");
-    InputData { value }
+    Backend::input_data(this)
 }
 
 pub fn onchange_handler(this: &Element) -> ChangeData {
-    match this.node_name().as_ref() {
-        "INPUT" => {
-            let input = this.dyn_ref::<InputElement>().unwrap();
-            let is_file = input
-                .get_attribute("type")
-                .map(|value| value.eq_ignore_ascii_case("file"))
-                .unwrap_or(false);
-            if is_file {
-                let files: FileList =  input.files().unwrap();
-                ChangeData::Files(files)
-            } else {
-                ChangeData::Value(input.value())
-            }
-        }
-        "TEXTAREA" => {
-            let tae = this.dyn_ref::<TextAreaElement>().unwrap();
-            ChangeData::Value(tae.value())
-        }
-        "SELECT" => {
-            let se = this.dyn_ref::<SelectElement>().unwrap().clone();
-            ChangeData::Select(se)
-        }
-        _ => {
-            unimplemented!("only an InputElement, TextAreaElement or SelectElement can have an onchange event listener");
-            //unimplemented!()
-        }
-    }
+    Backend::change_data(this)
 }
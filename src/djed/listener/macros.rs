@@ -10,21 +10,85 @@ macro_rules! impl_action {
             use crate::callback::Callback;
             #[allow(unused_imports)]
             use crate::djed::listener::*;
-            use crate::djed_dom::Listener;
-            use gloo::events::{EventListener, EventListenerOptions};
-            use wasm_bindgen::JsValue;
-            use web_sys::{$type as WebSysType, Element, EventTarget};
+            use crate::djed_dom::{Listener, ListenerOptions};
+            use crate::djed_dom::backend::{Backend, DomBackend, Element, ListenerHandle};
+            use crate::djed_dom::delegation::DelegatedHandler;
+            use crate::scheduler::{scheduler, Priority};
+            use std::any::Any;
+            use std::cell::Cell;
+            use std::rc::Rc;
+            use wasm_bindgen::JsCast;
+            use web_sys::$type as WebSysType;
 
             /// A wrapper for a callback which attaches event listeners to elements.
             #[derive(Clone, Debug)]
             pub struct Wrapper {
                 callback: Callback<Event>,
+                options: ListenerOptions,
             }
 
             impl Wrapper {
                 /// Create a wrapper for an event-typed callback
                 pub fn new(callback: Callback<Event>) -> Self {
-                    Wrapper { callback }
+                    Wrapper {
+                        callback,
+                        options: ListenerOptions::default(),
+                    }
+                }
+
+                /// Sets the options the listener is attached with.
+                pub fn with_options(mut self, options: ListenerOptions) -> Self {
+                    self.options = options;
+                    self
+                }
+
+                /// Listens in the capture phase instead of the bubble phase.
+                pub fn capture(mut self) -> Self {
+                    self.options.capture = true;
+                    self
+                }
+
+                /// Makes the listener passive or not, instead of the default which depends
+                /// on the event type.
+                pub fn passive(mut self, passive: bool) -> Self {
+                    self.options.passive = Some(passive);
+                    self
+                }
+
+                /// Calls the listener for the first event only.
+                pub fn once(mut self) -> Self {
+                    self.options.once = true;
+                    self
+                }
+
+                /// Prevents the default action of the events before calling the listener.
+                pub fn prevent_default(mut self) -> Self {
+                    self.options.prevent_default = true;
+                    self
+                }
+
+                /// Stops the propagation of the events before calling the listener.
+                pub fn stop_propagation(mut self) -> Self {
+                    self.options.stop_propagation = true;
+                    self
+                }
+
+                fn make_handler(&self) -> impl Fn(&Element, web_sys::Event) {
+                    let callback = self.callback.clone();
+                    let options = self.options;
+                    move |this: &Element, event: web_sys::Event| {
+                        if options.prevent_default {
+                            Backend::prevent_default(&event);
+                        }
+                        if options.stop_propagation {
+                            Backend::stop_propagation(&event);
+                        }
+                        let event: WebSysType = event.unchecked_into();
+                        // The messages sent by the listeners handle user input
+                        scheduler().with_priority(Priority::UserInput, || {
+                            callback.emit($convert(this, event))
+                        });
+                    }
                 }
             }
 
@@ -36,21 +100,43 @@ macro_rules! impl_action {
                     stringify!($action)
                 }
 
-                fn attach(&self, element: &Element) -> EventListener {
+                fn attach(&self, element: &Element) -> ListenerHandle {
                     let this = element.clone();
-                    let callback = self.callback.clone();
-                    let listener = move | event: &web_sys::Event | {
-                        let event: WebSysType = JsValue::from(event).into();
-                        callback.emit($convert(&this, event));
+                    let handler = self.make_handler();
+                    let once = self.options.once;
+                    let fired = Cell::new(false);
+                    let listener = move |event: web_sys::Event| {
+                        if once && fired.replace(true) {
+                            return;
+                        }
+                        handler(&this, event)
                     };
-                    // We should only set passive event listeners for `touchstart` and `touchmove`.
-                    // See here: https://developer.mozilla.org/en-US/docs/Web/API/EventTarget/addEventListener#Improving_scrolling_performance_with_passive_listeners
-                    if $name == "touchstart" || $name == "touchmove" {
-                        EventListener::new(&EventTarget::from(element.clone()), $name, listener)
-                    } else {
-                        let options = EventListenerOptions::enable_prevent_default();
-                        EventListener::new_with_options(&EventTarget::from(element.clone()), $name, options, listener)
+                    let options = self.options.to_event_listener_options($name);
+                    Backend::add_listener(element, $name, options, Box::new(listener))
+                }
+
+                fn handler(&self) -> Option<DelegatedHandler> {
+                    if $name.is_empty() {
+                        return None;
                     }
+                    Some(($name, Rc::new(self.make_handler())))
+                }
+
+                fn options(&self) -> ListenerOptions {
+                    self.options
+                }
+
+                fn as_any(&self) -> Option<&dyn Any> {
+                    Some(self)
+                }
+
+                fn same_as(&self, other: &dyn Listener) -> bool {
+                    other
+                        .as_any()
+                        .and_then(|other| other.downcast_ref::<Wrapper>())
+                        .map_or(false, |other| {
+                            self.callback == other.callback && self.options == other.options
+                        })
                 }
             }
         }
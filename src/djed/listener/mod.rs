@@ -5,4 +5,5 @@ mod listener;
 
 pub use listener_web_sys::*;
 pub use macros::*;
-pub use listener::{InputData, ChangeData/*, EventListener*/};
\ No newline at end of file
+pub use listener::{InputData, ChangeData/*, EventListener*/};
+pub use crate::djed_dom::ListenerOptions;
\ No newline at end of file
@@ -1,11 +1,20 @@
+mod context;
+mod error_boundary;
+pub mod functional;
 pub mod listener;
 mod scope;
 mod djed;
 
 pub use djed::{
     ChildrenRenderer, Component, Html, Children, ChildrenWithProps, NodeRef, Renderable, Props,
-    EmptyBuilder, ComponentLink, Href, ShouldRender
+    EmptyBuilder, ComponentLink, Href, PropsEqual, ShouldRender
 };
+pub use context::{ContextHandle, ContextProvider, ContextProviderProps};
+pub use error_boundary::{
+    ComponentError, ComponentErrorKind, ErrorBoundary, ErrorBoundaryProps, Fallback, RenderError,
+};
+pub(crate) use error_boundary::catch_panic;
+pub use functional::{FunctionComponent, FunctionProvider};
 pub use scope::{
     AnyScope, Scope, ComponentUpdate, Scoped,
 };
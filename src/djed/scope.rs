@@ -1,14 +1,21 @@
-use super::{Component, NodeRef, Renderable};
-use crate::callback::Callback;
+use super::{catch_panic, Component, ComponentError, ComponentErrorKind, NodeRef};
+use crate::callback::{AbortOnDrop, Callback};
+use crate::profiler::{Phase, RenderCause};
 use crate::scheduler::{scheduler, ComponentRunnableType, Runnable, Shared};
-use crate::djed_dom::{VNode, VDiff};
+use crate::djed_dom::{remove_unclaimed, VNode, VDiff, VRender};
 use std::any::{Any, TypeId};
-use std::cell::{Ref, RefCell};
+use std::cell::{Cell, Ref, RefCell};
 use std::fmt;
+use std::future::Future;
+use std::iter;
 use std::ops::Deref;
 use std::rc::Rc;
-use web_sys::Element;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::djed_dom::backend::{Backend, DomBackend, Element, Node};
+use crate::djed_dom::delegation::EventDelegation;
 
+// The identifier of the next scope
+static NEXT_SCOPE_ID: AtomicUsize = AtomicUsize::new(0);
 
 /// Updates for a `Component` instance. Used by scope sender.
 pub enum ComponentUpdate<COMP: Component> {
@@ -28,6 +35,11 @@ pub struct AnyScope {
     pub(crate) type_id: TypeId,
     pub(crate) parent: Option<Rc<AnyScope>>,
     pub(crate) state: Rc<dyn Any>,
+    pub(crate) delegation: Option<Rc<EventDelegation>>,
+    pub(crate) destroyed: Rc<Cell<bool>>,
+    pub(crate) futures: Rc<AbortOnDrop>,
+    pub(crate) id: usize,
+    pub(crate) pending_changes: Rc<Cell<usize>>,
 }
 
 impl<COMP: Component> From<Scope<COMP>> for AnyScope {
@@ -36,6 +48,11 @@ impl<COMP: Component> From<Scope<COMP>> for AnyScope {
             type_id: TypeId::of::<COMP>(),
             parent: scope.parent,
             state: Rc::new(scope.state),
+            delegation: scope.delegation,
+            destroyed: scope.destroyed,
+            futures: scope.futures,
+            id: scope.id,
+            pending_changes: scope.pending_changes,
         }
     }
 }
@@ -51,6 +68,15 @@ impl AnyScope {
         &self.type_id
     }
 
+    /// Returns the nearest ancestor scope of a component of type `COMP`
+    pub fn find_parent_scope<COMP: Component>(&self) -> Option<Scope<COMP>> {
+        let expected_type_id = TypeId::of::<COMP>();
+        iter::successors(self.get_parent(), |scope| scope.get_parent())
+            .find(|scope| scope.get_type_id() == &expected_type_id)
+            .cloned()
+            .map(AnyScope::downcast)
+    }
+
     /// Attempts to downcast into a typed scope
     pub fn downcast<COMP: Component>(self) -> Scope<COMP> {
         Scope {
@@ -60,6 +86,11 @@ impl AnyScope {
                 .downcast_ref::<Shared<Option<ComponentState<COMP>>>>()
                 .expect("unexpected component type")
                 .clone(),
+            delegation: self.delegation,
+            destroyed: self.destroyed,
+            futures: self.futures,
+            id: self.id,
+            pending_changes: self.pending_changes,
         }
     }
 }
@@ -96,8 +127,9 @@ impl<COMP: Component> Scoped for Scope<COMP> {
 
     /// Schedules a task to destroy a component
     fn destroy(&mut self) {
-        let state = self.state.clone();
-        let destroy = DestroyComponent { state };
+        let destroy = DestroyComponent {
+            scope: self.clone(),
+        };
         scheduler().push_comp(ComponentRunnableType::Destroy, Box::new(destroy));
     }
 }
@@ -106,6 +138,15 @@ impl<COMP: Component> Scoped for Scope<COMP> {
 pub struct Scope<COMP: Component> {
     parent: Option<Rc<AnyScope>>,
     state: Shared<Option<ComponentState<COMP>>>,
+    delegation: Option<Rc<EventDelegation>>,
+    destroyed: Rc<Cell<bool>>,
+    /// The futures spawned for the component, which are aborted when it is destroyed.
+    futures: Rc<AbortOnDrop>,
+    /// Identifies the component, unlike the address of its state which is reused once it is
+    /// dropped.
+    id: usize,
+    /// The number of property changes which are scheduled but didn't run yet.
+    pending_changes: Rc<Cell<usize>>,
 }
 
 impl<COMP: Component> fmt::Debug for Scope<COMP> {
@@ -119,6 +160,11 @@ impl<COMP: Component> Clone for Scope<COMP> {
         Scope {
             parent: self.parent.clone(),
             state: self.state.clone(),
+            delegation: self.delegation.clone(),
+            destroyed: self.destroyed.clone(),
+            futures: self.futures.clone(),
+            id: self.id,
+            pending_changes: self.pending_changes.clone(),
         }
     }
 }
@@ -129,6 +175,18 @@ impl<COMP: Component> Scope<COMP> {
         self.parent.as_deref()
     }
 
+    /// Identifies the component. The identifiers are assigned in the order the scopes are
+    /// created, and never reused once a component is destroyed.
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Returns `true` once the component was destroyed. Messages sent to it afterwards are
+    /// ignored.
+    pub fn is_destroyed(&self) -> bool {
+        self.destroyed.get()
+    }
+
     /// Returns the linked component if available
     pub fn get_component(&self) -> Option<impl Deref<Target = COMP> + '_> {
         self.state.try_borrow().ok().and_then(|state_ref| {
@@ -140,9 +198,26 @@ impl<COMP: Component> Scope<COMP> {
     }
 
     pub(crate) fn new(parent: Option<AnyScope>) -> Self {
+        // The events of the whole application are delegated to its mount element
+        let delegation = parent.as_ref().and_then(|parent| parent.delegation.clone());
         let parent = parent.map(Rc::new);
         let state = Rc::new(RefCell::new(None));
-        Scope { parent, state }
+        Scope {
+            parent,
+            state,
+            delegation,
+            destroyed: Rc::new(Cell::new(false)),
+            futures: Rc::default(),
+            id: NEXT_SCOPE_ID.fetch_add(1, Ordering::Relaxed),
+            pending_changes: Rc::default(),
+        }
+    }
+
+    /// Delegates the events of the elements rendered by the component and its descendants
+    /// to the `root` element.
+    pub(crate) fn delegate_events_to(mut self, root: &Element) -> Self {
+        self.delegation = Some(EventDelegation::new(root.clone()));
+        self
     }
 
     /// Mounts a component with `props` to the specified `element` in the DOM.
@@ -170,8 +245,125 @@ impl<COMP: Component> Scope<COMP> {
         self
     }
 
+    /// Hydrates a component with `props`, adopting the child nodes which the `element` in the
+    /// DOM already contains.
+    pub(crate) fn hydrate_in_place(self, parent: Element, props: COMP::Props) -> Scope<COMP> {
+        scheduler().push_comp(
+            ComponentRunnableType::Create,
+            Box::new(HydrateComponent {
+                parent,
+                scope: self.clone(),
+                props,
+            }),
+        );
+        self
+    }
+
+    /// Creates and renders a component right away, adopting the DOM nodes of `parent`
+    /// starting at `cursor`. It has to be called while the scheduler is running, so that
+    /// the messages sent by the component are handled once it is hydrated.
+    pub(crate) fn hydrate_nodes(
+        &self,
+        parent: Element,
+        next_sibling: NodeRef,
+        node_ref: NodeRef,
+        props: COMP::Props,
+        cursor: &mut Option<Node>,
+    ) {
+        let scope = self.clone();
+        catch_panic(
+            move || Some(scope),
+            || {
+                // The state is stored before the children are hydrated, so that they can find
+                // this component among their ancestors
+                let state = ComponentState::new(
+                    Some(parent.clone()),
+                    next_sibling.clone(),
+                    None,
+                    node_ref,
+                    self.clone(),
+                    props,
+                );
+                *self.state.borrow_mut() = Some(state);
+
+                let scheduler = scheduler();
+                let component = std::any::type_name::<COMP>();
+                scheduler.profile_render(component, self.id(), RenderCause::Force);
+                let mut root = match self.state.borrow().as_ref() {
+                    Some(state) => scheduler
+                        .profile(component, self.id(), Phase::View, || state.view())
+                        .unwrap_or_default(),
+                    None => return,
+                };
+                let node = scheduler.profile(component, self.id(), Phase::Apply, || {
+                    root.hydrate(&self.to_any(), &parent, next_sibling, cursor)
+                });
+
+                if let Some(state) = self.state.borrow_mut().as_mut() {
+                    state.node_ref.link(node);
+                    state.last_root = Some(root);
+                    scheduler.profile(component, self.id(), Phase::Rendered, || {
+                        state.component.rendered(true)
+                    });
+                }
+            },
+        );
+    }
+
+    /// Creates a component with `props` without mounting it and writes the HTML markup of its
+    /// view to `html`. Child components are rendered recursively.
+    pub(crate) fn render_html(&self, props: COMP::Props, svg: bool, html: &mut String) {
+        // The state is stored without a parent, so that the children can find this component
+        // among their ancestors
+        let state = ComponentState::new(
+            None,
+            NodeRef::default(),
+            None,
+            NodeRef::default(),
+            self.clone(),
+            props,
+        );
+        *self.state.borrow_mut() = Some(state);
+
+        let root = self.state.borrow().as_ref().and_then(ComponentState::view);
+        if let Some(root) = root {
+            root.render_html(Some(&self.to_any()), svg, html);
+        }
+
+        // The component is never mounted, so it is destroyed once rendered. Taking its state
+        // also breaks the cycle between the state and the scope it holds.
+        self.destroyed.set(true);
+        self.futures.abort_all();
+        let state = self.state.borrow_mut().take();
+        if let Some(mut state) = state {
+            state.component.destroy();
+        }
+    }
+
+    /// Schedules a task to change the properties of a component. The properties of a
+    /// memoized component which are equal to its current ones schedule nothing.
+    pub(crate) fn change(&self, props: COMP::Props, next_sibling: NodeRef) {
+        // A pending change would run after this one, so it can only be skipped when there is
+        // none. When a running task holds the state, the task of the change compares them.
+        if COMP::PROPS_EQUAL.is_some() && self.pending_changes.get() == 0 {
+            if let Ok(mut state) = self.state.try_borrow_mut() {
+                if let Some(state) = state.as_mut() {
+                    if state.props_unchanged(&props) {
+                        state.next_sibling = next_sibling;
+                        return;
+                    }
+                }
+            }
+        }
+        self.pending_changes.set(self.pending_changes.get() + 1);
+        self.update(ComponentUpdate::Properties(props, next_sibling), false);
+    }
+
     /// Schedules a task to send an update to a component
     pub(crate) fn update(&self, update: ComponentUpdate<COMP>, first_update: bool) {
+        if self.is_destroyed() {
+            return;
+        }
         let update = UpdateComponent {
             state: self.state.clone(),
             update,
@@ -187,7 +379,7 @@ impl<COMP: Component> Scope<COMP> {
             state,
             first_render,
         };
-        scheduler().push_comp(ComponentRunnableType::Render, Box::new(rendered));
+        scheduler().push_render(self.id(), Box::new(rendered));
     }
 
     /// Send a message to the component.
@@ -268,14 +460,54 @@ impl<COMP: Component> Scope<COMP> {
         };
         closure.into()
     }
+
+    /// Spawns the `future` and sends its output to the linked component's update method once
+    /// it resolves. The future is aborted when the component is destroyed, which drops it
+    /// with the scope it holds, so its output is never sent to a destroyed component.
+    pub fn send_future<F, M>(&self, future: F)
+    where
+        M: Into<COMP::State>,
+        F: Future<Output = M> + 'static,
+    {
+        if self.is_destroyed() {
+            return;
+        }
+        let scope = self.clone();
+        let (future, key) = self.futures.abortable(future);
+        Backend::spawn_local(Box::pin(async move {
+            if let Ok(message) = future.await {
+                scope.futures.remove(key);
+                scope.send_message(message);
+            }
+        }));
+    }
+
+    /// Creates a `Callback` which spawns the future returned by `function` when invoked,
+    /// and sends its output to the linked component's update method once it resolves.
+    /// See [`send_future`](Self::send_future).
+    pub fn callback_future<F, FU, IN, M>(&self, function: F) -> Callback<IN>
+    where
+        M: Into<COMP::State>,
+        FU: Future<Output = M> + 'static,
+        F: Fn(IN) -> FU + 'static,
+    {
+        let scope = self.clone();
+        let closure = move |input| {
+            scope.send_future(function(input));
+        };
+        closure.into()
+    }
 }
 
 struct ComponentState<COMP: Component> {
-    parent: Element,
+    /// The element the component is mounted to, or `None` if it is only rendered to a string.
+    parent: Option<Element>,
     next_sibling: NodeRef,
     node_ref: NodeRef,
     scope: Scope<COMP>,
     component: Box<COMP>,
+    /// The latest properties of a memoized component.
+    memoized_props: Option<COMP::Props>,
     placeholder: Option<VNode>,
     last_root: Option<VNode>,
     new_root: Option<VNode>,
@@ -285,13 +517,14 @@ impl<COMP: Component> ComponentState<COMP> {
     /// Creates a new `ComponentState`, also invokes the `create()`
     /// method on component to create it.
     fn new(
-        parent: Element,
+        parent: Option<Element>,
         next_sibling: NodeRef,
         placeholder: Option<VNode>,
         node_ref: NodeRef,
         scope: Scope<COMP>,
         props: COMP::Props,
     ) -> Self {
+        let memoized_props = COMP::PROPS_EQUAL.map(|_| props.clone());
         let component = Box::new(COMP::create(props, scope.clone()));
         Self {
             parent,
@@ -299,11 +532,36 @@ impl<COMP: Component> ComponentState<COMP> {
             node_ref,
             scope,
             component,
+            memoized_props,
             placeholder,
             last_root: None,
             new_root: None,
         }
     }
+
+    /// Returns `true` if the component is memoized and `props` are equal to its current ones.
+    fn props_unchanged(&self, props: &COMP::Props) -> bool {
+        match (COMP::PROPS_EQUAL, &self.memoized_props) {
+            (Some(props_equal), Some(memoized)) => props_equal(memoized, props),
+            _ => false,
+        }
+    }
+
+    /// Renders the view of the component. A failed view is reported to the nearest error
+    /// boundary, or logged if there is none.
+    fn view(&self) -> Option<VNode> {
+        match self.component.try_view() {
+            Ok(root) => Some(root),
+            Err(error) => {
+                let error =
+                    ComponentError::new::<COMP>(ComponentErrorKind::View, error.to_string());
+                if !self.scope.report_error(error.clone()) {
+                    log::error!("{}", error);
+                }
+                None
+            }
+        }
+    }
 }
 
 /// A `Runnable` task which creates the `ComponentState` (if there is
@@ -326,18 +584,60 @@ impl<COMP> Runnable for CreateComponent<COMP>
 where
     COMP: Component,
 {
+    fn component_name(&self) -> Option<&'static str> {
+        Some(std::any::type_name::<COMP>())
+    }
+
     fn run(self: Box<Self>) {
-        let mut current_state = self.state.borrow_mut();
-        if current_state.is_none() {
-            *current_state = Some(ComponentState::new(
-                self.parent,
-                self.next_sibling,
-                self.placeholder,
-                self.node_ref,
-                self.scope,
-                self.props,
-            ));
-        }
+        let scope = self.scope.clone();
+        catch_panic(
+            move || Some(scope),
+            move || {
+                let mut current_state = self.state.borrow_mut();
+                if current_state.is_none() {
+                    *current_state = Some(ComponentState::new(
+                        Some(self.parent),
+                        self.next_sibling,
+                        self.placeholder,
+                        self.node_ref,
+                        self.scope,
+                        self.props,
+                    ));
+                }
+            },
+        );
+    }
+}
+
+/// A `Runnable` task which creates a `Component` and hydrates the DOM nodes which
+/// were rendered in its parent element, for example by the server.
+struct HydrateComponent<COMP>
+where
+    COMP: Component,
+{
+    parent: Element,
+    scope: Scope<COMP>,
+    props: COMP::Props,
+}
+
+impl<COMP> Runnable for HydrateComponent<COMP>
+where
+    COMP: Component,
+{
+    fn component_name(&self) -> Option<&'static str> {
+        Some(std::any::type_name::<COMP>())
+    }
+
+    fn run(self: Box<Self>) {
+        let mut cursor = Backend::first_child(&self.parent);
+        self.scope.hydrate_nodes(
+            self.parent.clone(),
+            NodeRef::default(),
+            NodeRef::default(),
+            self.props,
+            &mut cursor,
+        );
+        remove_unclaimed(&self.parent, cursor);
     }
 }
 
@@ -354,25 +654,60 @@ impl<COMP> Runnable for UpdateComponent<COMP>
 where
     COMP: Component,
 {
+    fn component_name(&self) -> Option<&'static str> {
+        Some(std::any::type_name::<COMP>())
+    }
+
     fn run(self: Box<Self>) {
-        if let Some(mut state) = self.state.borrow_mut().as_mut() {
-            let should_update = match self.update {
-                ComponentUpdate::Force => true,
-                ComponentUpdate::Message(message) => state.component.update(message),
-                ComponentUpdate::MessageBatch(messages) => messages
-                    .into_iter()
-                    .fold(false, |acc, msg| state.component.update(msg) || acc),
-                ComponentUpdate::Properties(props, next_sibling) => {
-                    // When components are updated, their siblings were likely also updated
-                    state.next_sibling = next_sibling;
-                    state.component.change(props)
-                }
-            };
+        let state = self.state.clone();
+        catch_panic(
+            move || state.borrow().as_ref().map(|state| state.scope.clone()),
+            move || {
+                let UpdateComponent { state, update } = *self;
+                let mut state = state.borrow_mut();
+                if let Some(state) = state.as_mut() {
+                    let scheduler = scheduler();
+                    let component = std::any::type_name::<COMP>();
+                    let scope_id = state.scope.id();
+                    let (should_update, cause) =
+                        scheduler.profile(component, scope_id, Phase::Update, || {
+                            match update {
+                                ComponentUpdate::Force => (true, RenderCause::Force),
+                                ComponentUpdate::Message(message) => {
+                                    (state.component.update(message), RenderCause::Message)
+                                }
+                                ComponentUpdate::MessageBatch(messages) => {
+                                    let should_update = messages
+                                        .into_iter()
+                                        .fold(false, |acc, msg| state.component.update(msg) || acc);
+                                    (should_update, RenderCause::Batch)
+                                }
+                                ComponentUpdate::Properties(props, next_sibling) => {
+                                    let pending = &state.scope.pending_changes;
+                                    pending.set(pending.get() - 1);
+                                    // When components are updated, their siblings were likely
+                                    // also updated
+                                    state.next_sibling = next_sibling;
+                                    if state.props_unchanged(&props) {
+                                        (false, RenderCause::Props)
+                                    } else {
+                                        if COMP::PROPS_EQUAL.is_some() {
+                                            state.memoized_props = Some(props.clone());
+                                        }
+                                        (state.component.change(props), RenderCause::Props)
+                                    }
+                                }
+                            }
+                        });
 
-            if should_update {
-                state.new_root = Some(state.component.render());
-            };
-        }
+                    if should_update {
+                        scheduler.profile_render(component, scope_id, cause);
+                        state.new_root =
+                            scheduler.profile(component, scope_id, Phase::View, || state.view());
+                    };
+                }
+            },
+        );
     }
 }
 
@@ -389,23 +724,51 @@ impl<COMP> Runnable for RenderComponent<COMP>
 where
     COMP: Component,
 {
+    fn component_name(&self) -> Option<&'static str> {
+        Some(std::any::type_name::<COMP>())
+    }
+
     fn run(self: Box<Self>) {
-        if let Some(mut state) = self.state.borrow_mut().as_mut() {
-            // Skip render if we haven't seen the "first render" yet
-            if !self.first_render && state.last_root.is_none() {
-                return;
-            }
+        let state = self.state.clone();
+        catch_panic(
+            move || state.borrow().as_ref().map(|state| state.scope.clone()),
+            move || {
+                if let Some(mut state) = self.state.borrow_mut().as_mut() {
+                    // Skip render if we haven't seen the "first render" yet
+                    if !self.first_render && state.last_root.is_none() {
+                        return;
+                    }
+                    // Components rendered to a string have no parent and are never applied
+                    let parent = match &state.parent {
+                        Some(parent) => parent,
+                        None => return,
+                    };
 
-            if let Some(mut new_root) = state.new_root.take() {
-                let last_root = state.last_root.take().or_else(|| state.placeholder.take());
-                let parent_scope = state.scope.clone().into();
-                let next_sibling = state.next_sibling.clone();
-                let node = new_root.apply(&parent_scope, &state.parent, next_sibling, last_root);
-                state.node_ref.link(node);
-                state.last_root = Some(new_root);
-                state.component.rendered(self.first_render);
-            }
-        }
+                    if let Some(new_root) = state.new_root.take() {
+                        let scheduler = scheduler();
+                        let component = std::any::type_name::<COMP>();
+                        let scope_id = state.scope.id();
+                        let last_root = match state.last_root.take() {
+                            Some(last_root) => Some(last_root),
+                            None => state.placeholder.take(),
+                        };
+                        let parent_scope = state.scope.clone().into();
+                        let next_sibling = state.next_sibling.clone();
+                        // The new root is kept before it is applied, so that the nodes it
+                        // created are detached on destroy even if applying it panics.
+                        let new_root = state.last_root.get_or_insert(new_root);
+                        let node = scheduler.profile(component, scope_id, Phase::Apply, || {
+                            new_root.apply(&parent_scope, parent, next_sibling, last_root)
+                        });
+                        state.node_ref.link(node);
+                        let first_render = self.first_render;
+                        scheduler.profile(component, scope_id, Phase::Rendered, || {
+                            state.component.rendered(first_render)
+                        });
+                    }
+                }
+            },
+        );
     }
 }
 
@@ -414,22 +777,471 @@ struct DestroyComponent<COMP>
 where
     COMP: Component,
 {
-    state: Shared<Option<ComponentState<COMP>>>,
+    scope: Scope<COMP>,
 }
 
 impl<COMP> Runnable for DestroyComponent<COMP>
 where
     COMP: Component,
 {
+    fn component_name(&self) -> Option<&'static str> {
+        Some(std::any::type_name::<COMP>())
+    }
+
     fn run(self: Box<Self>) {
-        if let Some(mut state) = self.state.borrow_mut().take() {
+        self.scope.destroyed.set(true);
+        self.scope.futures.abort_all();
+        let scope = self.scope.clone();
+        // The component is destroyed while its view is still attached
+        catch_panic(
+            move || Some(scope),
+            || {
+                if let Some(state) = self.scope.state.borrow_mut().as_mut() {
+                    state.component.destroy();
+                }
+            },
+        );
+
+        if let Some(mut state) = self.scope.state.borrow_mut().take() {
             drop(state.component);
-            if let Some(last_frame) = &mut state.last_root {
-                last_frame.detach(&state.parent);
+            if let (Some(last_frame), Some(parent)) = (&mut state.last_root, &state.parent) {
+                last_frame.detach(parent);
             }
         }
     }
 }
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod native_tests {
+    use super::*;
+    use crate::djed::{ComponentLink, Html, Props, PropsEqual};
+    use crate::djed_dom::{Render, VComp, VTag, VText};
+    use crate::scheduler::{RunRecord, RunnableKind};
+    use crate::testing::TestRenderer;
+    use futures::channel::oneshot;
+    use std::marker::PhantomData;
+
+    thread_local! {
+        static LIFECYCLE: RefCell<Vec<String>> = RefCell::new(Vec::new());
+    }
+
+    fn log(entry: impl Into<String>) {
+        LIFECYCLE.with(|lifecycle| lifecycle.borrow_mut().push(entry.into()));
+    }
+
+    fn take_log() -> Vec<String> {
+        LIFECYCLE.with(|lifecycle| lifecycle.borrow_mut().drain(..).collect())
+    }
+
+    struct Item;
+
+    impl Component for Item {
+        type State = ();
+        type Props = ();
+
+        fn create(_: (), _: ComponentLink<Self>) -> Self {
+            Item
+        }
+
+        fn update(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn change(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn view(&self) -> Html {
+            let mut item = VTag::new("li");
+            item.add_child(VText::new("item".to_string()).into());
+            item.into()
+        }
+
+        fn destroy(&mut self) {
+            log("destroy item");
+        }
+    }
+
+    impl Drop for Item {
+        fn drop(&mut self) {
+            log("drop item");
+        }
+    }
+
+    struct List;
+
+    impl Component for List {
+        type State = ();
+        type Props = ();
+
+        fn create(_: (), _: ComponentLink<Self>) -> Self {
+            List
+        }
+
+        fn update(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn change(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn view(&self) -> Html {
+            let mut list = VTag::new("ul");
+            list.add_child(VComp::new::<Item>((), NodeRef::default(), None).into());
+            list.add_child(VComp::new::<Item>((), NodeRef::default(), None).into());
+            list.into()
+        }
+
+        fn destroy(&mut self) {
+            log("destroy list");
+        }
+    }
+
+    impl Drop for List {
+        fn drop(&mut self) {
+            log("drop list");
+        }
+    }
+
+    #[test]
+    fn render_to_string_destroys_the_components() {
+        let html = Render::<List>::new().render_to_string();
+        assert_eq!(html, "<ul><li>item</li><li>item</li></ul>");
+        assert_eq!(
+            take_log(),
+            vec![
+                "destroy item",
+                "drop item",
+                "destroy item",
+                "drop item",
+                "destroy list",
+                "drop list",
+            ]
+        );
+    }
+
+    #[derive(Clone, PartialEq)]
+    struct Label(&'static str);
+
+    impl Props for Label {
+        type Builder = ();
+
+        fn builder() {}
+    }
+
+    trait Memo: 'static {
+        const PROPS_EQUAL: Option<fn(&Label, &Label) -> bool>;
+        const NAME: &'static str;
+    }
+
+    struct Memoized;
+
+    impl Memo for Memoized {
+        const PROPS_EQUAL: Option<fn(&Label, &Label) -> bool> = Some(PartialEq::eq);
+        const NAME: &'static str = "memo";
+    }
+
+    struct Plain;
+
+    impl Memo for Plain {
+        const PROPS_EQUAL: Option<fn(&Label, &Label) -> bool> = None;
+        const NAME: &'static str = "plain";
+    }
+
+    /// Logs its views, and is memoized if `M` is `Memoized`.
+    struct Badge<M>(&'static str, PhantomData<M>);
+
+    impl<M: Memo> Component for Badge<M> {
+        type State = ();
+        type Props = Label;
+
+        const PROPS_EQUAL: Option<PropsEqual<Self>> = M::PROPS_EQUAL;
+
+        fn create(label: Label, _: ComponentLink<Self>) -> Self {
+            Badge(label.0, PhantomData)
+        }
+
+        fn update(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn change(&mut self, label: Label) -> bool {
+            self.0 = label.0;
+            true
+        }
+
+        fn view(&self) -> Html {
+            log(format!("view {} {}", M::NAME, self.0));
+            VText::new(self.0.to_string()).into()
+        }
+    }
+
+    /// Renders a memoized and a plain badge with its label.
+    struct Badges(&'static str);
+
+    impl Component for Badges {
+        type State = &'static str;
+        type Props = ();
+
+        fn create(_: (), _: ComponentLink<Self>) -> Self {
+            Badges("a")
+        }
+
+        fn update(&mut self, label: &'static str) -> bool {
+            self.0 = label;
+            true
+        }
+
+        fn change(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn view(&self) -> Html {
+            let mut badges = VTag::new("p");
+            let memo = VComp::new::<Badge<Memoized>>(Label(self.0), NodeRef::default(), None);
+            let plain = VComp::new::<Badge<Plain>>(Label(self.0), NodeRef::default(), None);
+            badges.add_child(memo.into());
+            badges.add_child(plain.into());
+            badges.into()
+        }
+    }
+
+    #[test]
+    fn memoized_component_skips_equal_props() {
+        let renderer = TestRenderer::<Badges>::mount();
+        assert_eq!(renderer.html(), "<p>aa</p>");
+        take_log();
+
+        renderer.link().send_message("a");
+        renderer.flush();
+        assert_eq!(take_log(), vec!["view plain a"]);
+
+        renderer.link().send_message("b");
+        renderer.flush();
+        assert_eq!(renderer.html(), "<p>bb</p>");
+        let mut views = take_log();
+        views.sort();
+        assert_eq!(views, vec!["view memo b", "view plain b"]);
+    }
+
+    #[test]
+    fn equal_props_of_memoized_component_schedule_nothing() {
+        let scheduler = scheduler();
+        scheduler.set_manual(true);
+        let root = Element::new_element("body");
+        let link = Render::<Badge<Memoized>>::new().mount_with_props(root.clone(), Label("a"));
+        scheduler.run_until_idle();
+        take_log();
+
+        link.change(Label("a"), NodeRef::default());
+        assert_eq!(scheduler.pending_count(), 0);
+
+        // The first change is still pending, so the second one is compared once it ran
+        link.change(Label("b"), NodeRef::default());
+        link.change(Label("a"), NodeRef::default());
+        assert_eq!(scheduler.pending_count(), 4);
+        scheduler.run_until_idle();
+        assert_eq!(root.inner_html(), "a");
+        assert_eq!(take_log(), vec!["view memo b", "view memo a"]);
+
+        let plain = Render::<Badge<Plain>>::new().mount_with_props(root, Label("a"));
+        scheduler.run_until_idle();
+        plain.change(Label("a"), NodeRef::default());
+        assert_eq!(scheduler.pending_count(), 2);
+        scheduler.run_until_idle();
+        scheduler.set_manual(false);
+    }
+
+    fn kinds(records: Vec<RunRecord>) -> Vec<RunnableKind> {
+        let item = Some(std::any::type_name::<Item>());
+        assert!(records.iter().all(|record| record.component == item));
+        records.into_iter().map(|record| record.kind).collect()
+    }
+
+    #[test]
+    fn manual_scheduler_records_the_lifecycle() {
+        let scheduler = scheduler();
+        scheduler.set_manual(true);
+        scheduler.set_recording(true);
+        let root = Element::new_element("body");
+        let link = Render::<Item>::new().mount_with_props(root.clone(), ());
+        assert_eq!(scheduler.pending_count(), 3);
+
+        assert!(scheduler.step());
+        assert_eq!(kinds(scheduler.take_records()), vec![RunnableKind::Create]);
+        assert!(scheduler.step());
+        assert_eq!(kinds(scheduler.take_records()), vec![RunnableKind::Update]);
+        assert_eq!(root.inner_html(), "");
+        assert!(scheduler.step());
+        assert_eq!(kinds(scheduler.take_records()), vec![RunnableKind::Render]);
+        assert_eq!(root.inner_html(), "<li>item</li>");
+        assert!(!scheduler.step());
+
+        link.send_message(());
+        assert_eq!(scheduler.pending_count(), 2);
+        assert_eq!(scheduler.run_until_idle(), 2);
+        assert_eq!(
+            kinds(scheduler.take_records()),
+            vec![RunnableKind::Update, RunnableKind::Render]
+        );
+
+        scheduler.set_recording(false);
+        link.send_message(());
+        assert_eq!(scheduler.run_until_idle(), 2);
+        assert!(scheduler.take_records().is_empty());
+        scheduler.set_manual(false);
+    }
+
+    /// Logs its renders, and shows how many messages it received.
+    struct Clicks(usize);
+
+    impl Component for Clicks {
+        type State = ();
+        type Props = ();
+
+        fn create(_: (), _: ComponentLink<Self>) -> Self {
+            Clicks(0)
+        }
+
+        fn update(&mut self, _: ()) -> bool {
+            self.0 += 1;
+            true
+        }
+
+        fn change(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn rendered(&mut self, _first_render: bool) {
+            log(format!("rendered {}", self.0));
+        }
+
+        fn view(&self) -> Html {
+            VText::new(self.0.to_string()).into()
+        }
+    }
+
+    #[test]
+    fn frame_batching_renders_once_per_frame() {
+        let renderer = TestRenderer::<Clicks>::mount();
+        take_log();
+        let scheduler = scheduler();
+        scheduler.set_frame_batching(true);
+        scheduler.set_recording(true);
+
+        renderer.link().send_message(());
+        renderer.link().send_message(());
+        assert_eq!(renderer.html(), "0");
+        assert!(take_log().is_empty());
+
+        scheduler.flush_frame();
+        assert_eq!(renderer.html(), "2");
+        assert_eq!(take_log(), vec!["rendered 2"]);
+        let renders = scheduler
+            .take_records()
+            .into_iter()
+            .filter(|record| record.kind == RunnableKind::Render)
+            .count();
+        assert_eq!(renders, 1);
+
+        scheduler.flush_frame();
+        assert!(take_log().is_empty());
+        scheduler.set_recording(false);
+        scheduler.set_frame_batching(false);
+    }
+
+    /// Logs whether its paragraph is still attached when it is destroyed.
+    struct Tracked {
+        link: ComponentLink<Self>,
+        paragraph: NodeRef,
+    }
+
+    impl Component for Tracked {
+        type State = ();
+        type Props = ();
+
+        fn create(_: (), link: ComponentLink<Self>) -> Self {
+            Tracked {
+                link,
+                paragraph: NodeRef::default(),
+            }
+        }
+
+        fn update(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn change(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn view(&self) -> Html {
+            let mut paragraph = VTag::new("p");
+            paragraph.node_ref = self.paragraph.clone();
+            paragraph.into()
+        }
+
+        fn destroy(&mut self) {
+            let attached = self
+                .paragraph
+                .get()
+                .and_then(|paragraph| Backend::parent_element(&paragraph))
+                .is_some();
+            log(format!(
+                "destroy attached: {} destroyed: {}",
+                attached,
+                self.link.is_destroyed()
+            ));
+        }
+    }
+
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            log("drop tracked");
+        }
+    }
+
+    #[test]
+    fn destroy_runs_while_attached_before_the_drop() {
+        let root = Element::new_element("body");
+        let mut link = Render::<Tracked>::new().mount_with_props(root.clone(), ());
+        scheduler().run_until_idle();
+        assert_eq!(root.inner_html(), "<p></p>");
+        assert!(!link.is_destroyed());
+
+        link.destroy();
+        scheduler().run_until_idle();
+        assert_eq!(
+            take_log(),
+            vec!["destroy attached: true destroyed: true", "drop tracked"]
+        );
+        assert_eq!(root.inner_html(), "");
+        assert!(link.is_destroyed());
+    }
+
+    #[test]
+    fn futures_are_aborted_when_the_component_is_destroyed() {
+        let renderer = TestRenderer::<Clicks>::mount();
+        let mut link = renderer.link().clone();
+        let (resolved, receiver) = oneshot::channel();
+        link.send_future(async move { receiver.await.unwrap_or_default() });
+        let (pending, receiver) = oneshot::channel();
+        link.send_future(async move { receiver.await.unwrap_or_default() });
+
+        resolved.send(()).unwrap();
+        renderer.flush();
+        assert_eq!(renderer.html(), "1");
+        take_log();
+
+        link.destroy();
+        renderer.flush();
+        assert!(pending.is_canceled());
+        assert!(pending.send(()).is_err());
+        renderer.flush();
+        assert!(take_log().is_empty());
+    }
+}
+
 /*
 #[cfg(test)]
 mod tests {
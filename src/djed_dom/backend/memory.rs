@@ -0,0 +1,903 @@
+use super::DomBackend;
+use crate::djed::listener::{ChangeData, InputData};
+use crate::djed_dom::v_dom::{escape_attribute, escape_text, Styles};
+use futures::executor::{LocalPool, LocalSpawner};
+use futures::task::LocalSpawnExt;
+use gloo::events::{EventListenerOptions, EventListenerPhase};
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::time::Duration;
+use wasm_bindgen::{JsCast, JsValue};
+
+/// The backend which applies virtual DOM trees to an in-memory tree of [`MemoryNode`]s.
+///
+/// # Events
+///
+/// The `web_sys` events given to the listeners of the in-memory DOM wrap `undefined`, and
+/// calling any of their methods panics outside of the browser. Their data is kept by the
+/// backend instead, and read with the methods of [`DomBackend`] on any target:
+/// - `Backend::event_target` returns the node an event was dispatched to,
+/// - `Backend::event_key` returns the key of the events dispatched with
+///   [`MemoryNode::dispatch_keyboard_event`],
+/// - `oninput` and `onchange` read their data from their element,
+/// - the `prevent_default` and `stop_propagation` options of the listeners are handled by
+///   the backend.
+///
+/// Listeners which ignore their event, like `link.callback(|_| Msg::Click)`, are supported
+/// too.
+///
+/// # Timeouts and futures
+///
+/// The in-memory timeouts are called when the time of the backend is advanced with
+/// [`MemoryBackend::advance_time`], and the spawned futures are polled with
+/// [`MemoryBackend::run_futures`]. Nothing runs in the background.
+#[derive(Debug)]
+pub struct MemoryBackend;
+
+/// A node of the in-memory DOM. Clones refer to the same node.
+#[derive(Clone)]
+pub struct MemoryNode(Rc<RefCell<NodeData>>);
+
+struct NodeData {
+    kind: NodeKind,
+    parent: Weak<RefCell<NodeData>>,
+    children: Vec<MemoryNode>,
+}
+
+enum NodeKind {
+    Element(ElementData),
+    Text(String),
+}
+
+struct ElementData {
+    tag: String,
+    namespace: Option<String>,
+    attributes: BTreeMap<String, String>,
+    properties: BTreeMap<String, MemoryValue>,
+    value: String,
+    checked: bool,
+    selected: bool,
+    listeners: Vec<RegisteredListener>,
+    next_listener_id: usize,
+    // Markup set with `set_inner_html`, which is kept as is instead of being parsed
+    raw_html: Option<String>,
+    delegation_key: Option<usize>,
+}
+
+thread_local! {
+    // The target of the event being dispatched, since the events carry no data
+    static DISPATCH_TARGET: RefCell<Option<MemoryNode>> = const { RefCell::new(None) };
+    // The key of the keyboard event being dispatched
+    static DISPATCH_KEY: RefCell<Option<String>> = const { RefCell::new(None) };
+    // Whether a listener prevented the default action of the event being dispatched
+    static DEFAULT_PREVENTED: Cell<bool> = const { Cell::new(false) };
+    // Whether a listener stopped the propagation of the event being dispatched
+    static PROPAGATION_STOPPED: Cell<bool> = const { Cell::new(false) };
+    // The timeouts waiting for the time of the backend to be advanced
+    static TIMEOUTS: RefCell<Timeouts> = RefCell::new(Timeouts::default());
+    // The futures spawned on the backend, and the handle which spawns them while they run
+    static EXECUTOR: (RefCell<LocalPool>, LocalSpawner) = {
+        let pool = LocalPool::new();
+        let spawner = pool.spawner();
+        (RefCell::new(pool), spawner)
+    };
+    // The document, an `<html>` element which contains a `<body>`
+    static DOCUMENT: MemoryNode = {
+        let html = MemoryNode::new_element("html");
+        MemoryBackend::insert_before(&html, &MemoryNode::new_element("body"), None);
+        html
+    };
+}
+
+/// A value of a property of a [`MemoryNode`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum MemoryValue {
+    /// A boolean.
+    Bool(bool),
+    /// A number.
+    Number(f64),
+    /// A string.
+    String(String),
+}
+
+impl fmt::Display for MemoryValue {
+    /// Writes the value as a JavaScript literal.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MemoryValue::Bool(value) => write!(f, "{}", value),
+            MemoryValue::Number(value) => write!(f, "{}", value),
+            MemoryValue::String(value) => write!(f, "{:?}", value),
+        }
+    }
+}
+
+impl From<bool> for MemoryValue {
+    fn from(value: bool) -> Self {
+        MemoryValue::Bool(value)
+    }
+}
+
+impl From<i32> for MemoryValue {
+    fn from(value: i32) -> Self {
+        MemoryValue::Number(value.into())
+    }
+}
+
+impl From<f64> for MemoryValue {
+    fn from(value: f64) -> Self {
+        MemoryValue::Number(value)
+    }
+}
+
+impl From<String> for MemoryValue {
+    fn from(value: String) -> Self {
+        MemoryValue::String(value)
+    }
+}
+
+impl<'a> From<&'a str> for MemoryValue {
+    fn from(value: &'a str) -> Self {
+        MemoryValue::String(value.to_owned())
+    }
+}
+
+struct RegisteredListener {
+    id: usize,
+    event_type: &'static str,
+    capture: bool,
+    listener: Rc<dyn Fn(web_sys::Event)>,
+}
+
+/// A handle which detaches its listener from a [`MemoryNode`] when it is dropped.
+pub struct MemoryListenerHandle {
+    element: Weak<RefCell<NodeData>>,
+    id: usize,
+}
+
+impl fmt::Debug for MemoryListenerHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MemoryListenerHandle")
+    }
+}
+
+impl Drop for MemoryListenerHandle {
+    fn drop(&mut self) {
+        if let Some(data) = self.element.upgrade() {
+            if let NodeKind::Element(element) = &mut data.borrow_mut().kind {
+                element.listeners.retain(|listener| listener.id != self.id);
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Timeouts {
+    now: Duration,
+    next_id: usize,
+    pending: Vec<PendingTimeout>,
+}
+
+struct PendingTimeout {
+    id: usize,
+    due: Duration,
+    callback: Box<dyn FnOnce()>,
+}
+
+/// A handle which cancels its timeout of the in-memory backend when it is dropped.
+pub struct MemoryTimeoutHandle {
+    id: usize,
+}
+
+impl fmt::Debug for MemoryTimeoutHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MemoryTimeoutHandle")
+    }
+}
+
+impl Drop for MemoryTimeoutHandle {
+    fn drop(&mut self) {
+        // The callback is dropped once the timeouts are released, since it may drop handles
+        let _cancelled = TIMEOUTS.try_with(|timeouts| {
+            let mut timeouts = timeouts.borrow_mut();
+            let index = timeouts
+                .pending
+                .iter()
+                .position(|timeout| timeout.id == self.id)?;
+            Some(timeouts.pending.remove(index))
+        });
+    }
+}
+
+impl MemoryBackend {
+    /// Advances the time of the backend by `duration`, and calls the timeouts which are due
+    /// in the order they are due.
+    pub fn advance_time(duration: Duration) {
+        let end = TIMEOUTS.with(|timeouts| timeouts.borrow().now + duration);
+        loop {
+            let next = TIMEOUTS.with(|timeouts| {
+                let mut timeouts = timeouts.borrow_mut();
+                let (index, _) = timeouts
+                    .pending
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, timeout)| timeout.due <= end)
+                    .min_by_key(|(_, timeout)| (timeout.due, timeout.id))?;
+                let timeout = timeouts.pending.remove(index);
+                timeouts.now = timeout.due;
+                Some(timeout.callback)
+            });
+            match next {
+                Some(callback) => callback(),
+                None => break,
+            }
+        }
+        TIMEOUTS.with(|timeouts| timeouts.borrow_mut().now = end);
+    }
+
+    /// Polls the spawned futures until none of them can make progress.
+    pub fn run_futures() {
+        EXECUTOR.with(|(pool, _)| {
+            // A future which runs the futures itself is already being polled by them
+            if let Ok(mut pool) = pool.try_borrow_mut() {
+                pool.run_until_stalled();
+            }
+        });
+    }
+}
+
+impl MemoryNode {
+    fn new(kind: NodeKind) -> Self {
+        MemoryNode(Rc::new(RefCell::new(NodeData {
+            kind,
+            parent: Weak::new(),
+            children: Vec::new(),
+        })))
+    }
+
+    /// Creates a detached element, for example to mount a component to.
+    pub fn new_element(tag: &str) -> Self {
+        MemoryNode::new(NodeKind::Element(ElementData {
+            tag: tag.to_owned(),
+            namespace: None,
+            attributes: BTreeMap::new(),
+            properties: BTreeMap::new(),
+            value: String::new(),
+            checked: false,
+            selected: false,
+            listeners: Vec::new(),
+            next_listener_id: 0,
+            raw_html: None,
+            delegation_key: None,
+        }))
+    }
+
+    /// Creates a detached text node.
+    pub fn new_text(text: &str) -> Self {
+        MemoryNode::new(NodeKind::Text(text.to_owned()))
+    }
+
+    fn with_element<R>(&self, f: impl FnOnce(&mut ElementData) -> R) -> Option<R> {
+        match &mut self.0.borrow_mut().kind {
+            NodeKind::Element(element) => Some(f(element)),
+            NodeKind::Text(_) => None,
+        }
+    }
+
+    /// Returns `true` if the node is an element.
+    pub fn is_element(&self) -> bool {
+        self.tag_name().is_some()
+    }
+
+    /// Returns `true` if the node is a text node.
+    pub fn is_text(&self) -> bool {
+        self.text().is_some()
+    }
+
+    /// Returns the tag of an element.
+    pub fn tag_name(&self) -> Option<String> {
+        match &self.0.borrow().kind {
+            NodeKind::Element(element) => Some(element.tag.clone()),
+            NodeKind::Text(_) => None,
+        }
+    }
+
+    /// Returns the namespace of an element.
+    pub fn namespace_uri(&self) -> Option<String> {
+        match &self.0.borrow().kind {
+            NodeKind::Element(element) => element.namespace.clone(),
+            NodeKind::Text(_) => None,
+        }
+    }
+
+    /// Returns the content of a text node.
+    pub fn text(&self) -> Option<String> {
+        match &self.0.borrow().kind {
+            NodeKind::Element(_) => None,
+            NodeKind::Text(text) => Some(text.clone()),
+        }
+    }
+
+    /// Returns the concatenated content of all text nodes in the subtree of the node.
+    pub fn text_content(&self) -> String {
+        match &self.0.borrow().kind {
+            NodeKind::Text(text) => text.clone(),
+            NodeKind::Element(_) => self
+                .children()
+                .iter()
+                .map(MemoryNode::text_content)
+                .collect(),
+        }
+    }
+
+    /// Returns the value of an attribute of an element.
+    pub fn attribute(&self, name: &str) -> Option<String> {
+        match &self.0.borrow().kind {
+            NodeKind::Element(element) => element.attributes.get(name).cloned(),
+            NodeKind::Text(_) => None,
+        }
+    }
+
+    /// Returns the attributes of an element sorted by name.
+    pub fn attributes(&self) -> Vec<(String, String)> {
+        match &self.0.borrow().kind {
+            NodeKind::Element(element) => element
+                .attributes
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
+            NodeKind::Text(_) => Vec::new(),
+        }
+    }
+
+    /// Returns the value of a property set on an element.
+    pub fn property(&self, name: &str) -> Option<MemoryValue> {
+        match &self.0.borrow().kind {
+            NodeKind::Element(element) => element.properties.get(name).cloned(),
+            NodeKind::Text(_) => None,
+        }
+    }
+
+    /// Returns the properties set on an element sorted by name.
+    pub fn properties(&self) -> Vec<(String, MemoryValue)> {
+        match &self.0.borrow().kind {
+            NodeKind::Element(element) => element
+                .properties
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
+            NodeKind::Text(_) => Vec::new(),
+        }
+    }
+
+    /// Returns the `value` of an element.
+    pub fn value(&self) -> Option<String> {
+        match &self.0.borrow().kind {
+            NodeKind::Element(element) => Some(element.value.clone()),
+            NodeKind::Text(_) => None,
+        }
+    }
+
+    /// Returns the `checked` state of an element.
+    pub fn checked(&self) -> bool {
+        match &self.0.borrow().kind {
+            NodeKind::Element(element) => element.checked,
+            NodeKind::Text(_) => false,
+        }
+    }
+
+    /// Returns the selectedness of an `<option>`.
+    pub fn selected(&self) -> bool {
+        match &self.0.borrow().kind {
+            NodeKind::Element(element) => element.selected,
+            NodeKind::Text(_) => false,
+        }
+    }
+
+    // The `<option>` elements of a `<select>`, including the ones in an `<optgroup>`
+    fn options(&self) -> Vec<MemoryNode> {
+        let mut options = Vec::new();
+        for child in self.children() {
+            match child.tag_name().map(|tag| tag.to_ascii_lowercase()).as_deref() {
+                Some("option") => options.push(child),
+                Some("optgroup") => options.extend(child.options()),
+                _ => {}
+            }
+        }
+        options
+    }
+
+    // The value of an `<option>` defaults to its text
+    fn option_value(&self) -> String {
+        self.attribute("value")
+            .unwrap_or_else(|| self.text_content().trim().to_owned())
+    }
+
+    /// Returns the markup set as the inner HTML of an element. The in-memory DOM doesn't parse
+    /// it, so it replaces the children of the element until a child is inserted.
+    pub fn raw_html(&self) -> Option<String> {
+        match &self.0.borrow().kind {
+            NodeKind::Element(element) => element.raw_html.clone(),
+            NodeKind::Text(_) => None,
+        }
+    }
+
+    /// Returns the event types of the listeners attached to an element.
+    pub fn listeners(&self) -> Vec<&'static str> {
+        match &self.0.borrow().kind {
+            NodeKind::Element(element) => element
+                .listeners
+                .iter()
+                .map(|listener| listener.event_type)
+                .collect(),
+            NodeKind::Text(_) => Vec::new(),
+        }
+    }
+
+    /// Returns the identifiers of the listeners attached to an element, which change when a
+    /// listener is attached again.
+    #[cfg(test)]
+    pub(crate) fn listener_ids(&self) -> Vec<usize> {
+        match &self.0.borrow().kind {
+            NodeKind::Element(element) => {
+                element.listeners.iter().map(|listener| listener.id).collect()
+            }
+            NodeKind::Text(_) => Vec::new(),
+        }
+    }
+
+    /// Calls the listeners for `event_type` events attached to the node and its ancestors,
+    /// like a bubbling event does: the capture listeners from the root down to the node, then
+    /// the other listeners from the node up to the root. The event passed to the listeners
+    /// wraps `undefined`, so its data is read through the backend, like its target with
+    /// `Backend::event_target`, and listeners which call its methods panic, see
+    /// [`MemoryBackend`].
+    /// Once a listener stopped the propagation of the event, the listeners of the next nodes
+    /// aren't called. Returns `false` if a listener prevented the default action of the
+    /// event, like `dispatchEvent` does.
+    pub fn dispatch_event(&self, event_type: &str) -> bool {
+        let previous_target = DISPATCH_TARGET.with(|target| target.replace(Some(self.clone())));
+        let previous_prevented = DEFAULT_PREVENTED.with(|prevented| prevented.replace(false));
+        let previous_stopped = PROPAGATION_STOPPED.with(|stopped| stopped.replace(false));
+        let path: Vec<MemoryNode> =
+            std::iter::successors(Some(self.clone()), MemoryNode::parent).collect();
+        let capture_path = path.iter().rev().map(|node| (node, true));
+        let bubble_path = path.iter().map(|node| (node, false));
+        for (node, capture) in capture_path.chain(bubble_path) {
+            let listeners: Vec<Rc<dyn Fn(web_sys::Event)>> = match &node.0.borrow().kind {
+                NodeKind::Element(element) => element
+                    .listeners
+                    .iter()
+                    .filter(|listener| listener.event_type == event_type)
+                    .filter(|listener| listener.capture == capture)
+                    .map(|listener| listener.listener.clone())
+                    .collect(),
+                NodeKind::Text(_) => Vec::new(),
+            };
+            for listener in listeners {
+                listener(JsValue::UNDEFINED.unchecked_into());
+            }
+            if PROPAGATION_STOPPED.with(Cell::get) {
+                break;
+            }
+        }
+        DISPATCH_TARGET.with(|target| *target.borrow_mut() = previous_target);
+        PROPAGATION_STOPPED.with(|stopped| stopped.set(previous_stopped));
+        !DEFAULT_PREVENTED.with(|prevented| prevented.replace(previous_prevented))
+    }
+
+    /// Dispatches a keyboard event like [`dispatch_event`](Self::dispatch_event), whose key
+    /// is returned by `Backend::event_key` while the listeners run.
+    pub fn dispatch_keyboard_event(&self, event_type: &str, key: &str) -> bool {
+        let previous_key = DISPATCH_KEY.with(|dispatched| dispatched.replace(Some(key.into())));
+        let not_prevented = self.dispatch_event(event_type);
+        DISPATCH_KEY.with(|dispatched| *dispatched.borrow_mut() = previous_key);
+        not_prevented
+    }
+
+    /// Returns the children of the node.
+    pub fn children(&self) -> Vec<MemoryNode> {
+        self.0.borrow().children.clone()
+    }
+
+    /// Returns the parent of the node.
+    pub fn parent(&self) -> Option<MemoryNode> {
+        self.0.borrow().parent.upgrade().map(MemoryNode)
+    }
+
+    /// Returns the markup of the children of the node.
+    pub fn inner_html(&self) -> String {
+        let mut html = self.raw_html().unwrap_or_default();
+        for child in self.children() {
+            child.write_html(&mut html);
+        }
+        html
+    }
+
+    /// Returns the markup of the node and its children.
+    pub fn outer_html(&self) -> String {
+        let mut html = String::new();
+        self.write_html(&mut html);
+        html
+    }
+
+    fn write_html(&self, html: &mut String) {
+        let tag = match &self.0.borrow().kind {
+            NodeKind::Text(text) => {
+                escape_text(text, html);
+                return;
+            }
+            NodeKind::Element(element) => {
+                html.push('<');
+                html.push_str(&element.tag);
+                for (name, value) in element.attributes.iter() {
+                    html.push(' ');
+                    html.push_str(name);
+                    html.push_str("=\"");
+                    escape_attribute(value, html);
+                    html.push('"');
+                }
+                html.push('>');
+                element.tag.clone()
+            }
+        };
+        html.push_str(&self.inner_html());
+        html.push_str("</");
+        html.push_str(&tag);
+        html.push('>');
+    }
+
+    fn index_in(&self, parent: &MemoryNode) -> Option<usize> {
+        parent
+            .0
+            .borrow()
+            .children
+            .iter()
+            .position(|child| child == self)
+    }
+
+    fn detach_from_parent(&self) {
+        if let Some(parent) = self.parent() {
+            if let Some(index) = self.index_in(&parent) {
+                parent.0.borrow_mut().children.remove(index);
+            }
+        }
+        self.0.borrow_mut().parent = Weak::new();
+    }
+}
+
+impl PartialEq for MemoryNode {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl fmt::Debug for MemoryNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.outer_html())
+    }
+}
+
+impl DomBackend for MemoryBackend {
+    type Node = MemoryNode;
+    type Element = MemoryNode;
+    type Text = MemoryNode;
+    type Value = MemoryValue;
+    type ListenerHandle = MemoryListenerHandle;
+    type TimeoutHandle = MemoryTimeoutHandle;
+
+    fn document_element() -> MemoryNode {
+        DOCUMENT.with(Clone::clone)
+    }
+
+    fn body() -> MemoryNode {
+        MemoryBackend::document_element()
+            .children()
+            .into_iter()
+            .find(|child| child.tag_name().as_deref() == Some("body"))
+            .expect("can't unwrap body node")
+    }
+
+    fn create_element(tag: &str, namespace: Option<&str>) -> MemoryNode {
+        let element = MemoryNode::new_element(tag);
+        element.with_element(|element| element.namespace = namespace.map(str::to_owned));
+        element
+    }
+
+    fn create_text(text: &str) -> MemoryNode {
+        MemoryNode::new_text(text)
+    }
+
+    fn namespace_uri(element: &MemoryNode) -> Option<String> {
+        element.namespace_uri()
+    }
+
+    fn tag_name(element: &MemoryNode) -> String {
+        element.tag_name().unwrap_or_default()
+    }
+
+    fn node_name(node: &MemoryNode) -> String {
+        node.tag_name().unwrap_or_else(|| "#text".to_owned())
+    }
+
+    fn as_element(node: &MemoryNode) -> Option<MemoryNode> {
+        Some(node.clone()).filter(MemoryNode::is_element)
+    }
+
+    fn as_text(node: &MemoryNode) -> Option<MemoryNode> {
+        Some(node.clone()).filter(MemoryNode::is_text)
+    }
+
+    fn insert_before(parent: &MemoryNode, node: &MemoryNode, next_sibling: Option<&MemoryNode>) {
+        node.detach_from_parent();
+        let index = match next_sibling {
+            Some(next_sibling) => next_sibling
+                .index_in(parent)
+                .expect("failed to insert tag before next sibling"),
+            None => parent.0.borrow().children.len(),
+        };
+        parent.0.borrow_mut().children.insert(index, node.clone());
+        parent.with_element(|element| element.raw_html = None);
+        node.0.borrow_mut().parent = Rc::downgrade(&parent.0);
+    }
+
+    fn remove_child(parent: &MemoryNode, node: &MemoryNode) -> bool {
+        if node.index_in(parent).is_none() {
+            return false;
+        }
+        node.detach_from_parent();
+        true
+    }
+
+    fn first_child(element: &MemoryNode) -> Option<MemoryNode> {
+        element.0.borrow().children.first().cloned()
+    }
+
+    fn last_child(element: &MemoryNode) -> Option<MemoryNode> {
+        element.0.borrow().children.last().cloned()
+    }
+
+    fn next_sibling(node: &MemoryNode) -> Option<MemoryNode> {
+        let parent = node.parent()?;
+        let index = node.index_in(&parent)?;
+        let sibling = parent.0.borrow().children.get(index + 1).cloned();
+        sibling
+    }
+
+    fn parent_element(node: &MemoryNode) -> Option<MemoryNode> {
+        node.parent()
+    }
+
+    fn set_attribute(element: &MemoryNode, name: &str, value: &str) {
+        element.with_element(|element| {
+            element.attributes.insert(name.to_owned(), value.to_owned())
+        });
+    }
+
+    fn remove_attribute(element: &MemoryNode, name: &str) {
+        element.with_element(|element| element.attributes.remove(name));
+    }
+
+    fn attribute_names(element: &MemoryNode) -> Vec<String> {
+        element
+            .attributes()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    fn set_style(element: &MemoryNode, property: &str, value: &str, important: bool) {
+        // Like in browsers, the `style` attribute reflects the inline styles
+        let mut styles = Styles::from(element.attribute("style"));
+        if important {
+            styles.set_important(property, value);
+        } else {
+            styles.set(property, value);
+        }
+        MemoryBackend::set_attribute(element, "style", &styles.to_string());
+    }
+
+    fn remove_style(element: &MemoryNode, property: &str) {
+        let mut styles = Styles::from(element.attribute("style"));
+        styles.remove(property);
+        if styles.is_empty() {
+            MemoryBackend::remove_attribute(element, "style");
+        } else {
+            MemoryBackend::set_attribute(element, "style", &styles.to_string());
+        }
+    }
+
+    fn set_property(element: &MemoryNode, name: &str, value: &MemoryValue) {
+        element.with_element(|element| {
+            element.properties.insert(name.to_owned(), value.clone())
+        });
+    }
+
+    fn remove_property(element: &MemoryNode, name: &str) {
+        element.with_element(|element| element.properties.remove(name));
+    }
+
+    fn text(text: &MemoryNode) -> String {
+        text.text().unwrap_or_default()
+    }
+
+    fn set_text(text: &MemoryNode, value: &str) {
+        if let NodeKind::Text(text) = &mut text.0.borrow_mut().kind {
+            *text = value.to_owned();
+        }
+    }
+
+    fn split_text(text: &MemoryNode, prefix: &str) {
+        let content = MemoryBackend::text(text);
+        let rest = MemoryNode::new_text(&content[prefix.len()..]);
+        MemoryBackend::set_text(text, prefix);
+        if let Some(parent) = text.parent() {
+            let next_sibling = MemoryBackend::next_sibling(text);
+            MemoryBackend::insert_before(&parent, &rest, next_sibling.as_ref());
+        }
+    }
+
+    fn set_inner_html(element: &MemoryNode, html: &str) {
+        for child in element.children() {
+            child.detach_from_parent();
+        }
+        element.with_element(|element| element.raw_html = Some(html.to_owned()));
+    }
+
+    fn value(element: &MemoryNode) -> Option<String> {
+        match element.tag_name()?.to_ascii_lowercase().as_str() {
+            "input" | "textarea" => element.value(),
+            "select" => {
+                // Like browsers, a single select without a selected option selects the first
+                let options = element.options();
+                let selected = options.iter().find(|option| option.selected());
+                let multiple = element.attribute("multiple").is_some();
+                let option = selected.or_else(|| options.first().filter(|_| !multiple));
+                Some(option.map(MemoryNode::option_value).unwrap_or_default())
+            }
+            _ => None,
+        }
+    }
+
+    fn set_value(element: &MemoryNode, value: &str) {
+        if element
+            .tag_name()
+            .is_some_and(|tag| tag.eq_ignore_ascii_case("select"))
+        {
+            let mut found = false;
+            for option in element.options() {
+                let selected = !found && option.option_value() == value;
+                found |= selected;
+                MemoryBackend::set_selected(&option, selected);
+            }
+        } else {
+            element.with_element(|element| element.value = value.to_owned());
+        }
+    }
+
+    fn set_checked(element: &MemoryNode, checked: bool) {
+        element.with_element(|element| element.checked = checked);
+    }
+
+    fn set_selected(element: &MemoryNode, selected: bool) {
+        element.with_element(|element| element.selected = selected);
+    }
+
+    fn selected_values(element: &MemoryNode) -> Vec<String> {
+        element
+            .options()
+            .iter()
+            .filter(|option| option.selected())
+            .map(MemoryNode::option_value)
+            .collect()
+    }
+
+    fn set_selected_values(element: &MemoryNode, values: &[String]) {
+        for option in element.options() {
+            let selected = values.contains(&option.option_value());
+            MemoryBackend::set_selected(&option, selected);
+        }
+    }
+
+    fn set_kind(element: &MemoryNode, kind: &str) {
+        MemoryBackend::set_attribute(element, "type", kind);
+    }
+
+    fn add_listener(
+        element: &MemoryNode,
+        event_type: &'static str,
+        options: EventListenerOptions,
+        listener: Box<dyn Fn(web_sys::Event)>,
+    ) -> MemoryListenerHandle {
+        let id = element
+            .with_element(|element| {
+                let id = element.next_listener_id;
+                element.next_listener_id += 1;
+                element.listeners.push(RegisteredListener {
+                    id,
+                    event_type,
+                    capture: matches!(options.phase, EventListenerPhase::Capture),
+                    listener: Rc::from(listener),
+                });
+                id
+            })
+            .expect("listeners can only be attached to elements");
+        MemoryListenerHandle {
+            element: Rc::downgrade(&element.0),
+            id,
+        }
+    }
+
+    fn event_target(_event: &web_sys::Event) -> Option<MemoryNode> {
+        DISPATCH_TARGET.with(|target| target.borrow().clone())
+    }
+
+    fn event_key(_event: &web_sys::KeyboardEvent) -> String {
+        DISPATCH_KEY.with(|key| key.borrow().clone().unwrap_or_default())
+    }
+
+    fn prevent_default(_event: &web_sys::Event) {
+        DEFAULT_PREVENTED.with(|prevented| prevented.set(true));
+    }
+
+    fn stop_propagation(_event: &web_sys::Event) {
+        PROPAGATION_STOPPED.with(|stopped| stopped.set(true));
+    }
+
+    fn share_event(_event: &web_sys::Event) -> web_sys::Event {
+        // The in-memory events wrap `undefined`, which can't be cloned natively
+        JsValue::UNDEFINED.unchecked_into()
+    }
+
+    fn propagation_stopped(_event: &web_sys::Event) -> bool {
+        PROPAGATION_STOPPED.with(Cell::get)
+    }
+
+    fn delegation_key(element: &MemoryNode) -> Option<usize> {
+        element.with_element(|element| element.delegation_key).flatten()
+    }
+
+    fn set_delegation_key(element: &MemoryNode, key: Option<usize>) {
+        element.with_element(|element| element.delegation_key = key);
+    }
+
+    fn input_data(this: &MemoryNode) -> InputData {
+        let value = MemoryBackend::value(this).unwrap_or_else(|| this.text_content());
+        InputData { value }
+    }
+
+    fn change_data(this: &MemoryNode) -> ChangeData {
+        let is_select = this
+            .tag_name()
+            .map_or(false, |tag| tag.eq_ignore_ascii_case("select"));
+        if !is_select {
+            ChangeData::Value(this.value().unwrap_or_default())
+        } else if this.attribute("multiple").is_some() {
+            ChangeData::Values(MemoryBackend::selected_values(this))
+        } else {
+            ChangeData::Value(MemoryBackend::value(this).unwrap_or_default())
+        }
+    }
+
+    fn set_timeout(duration: Duration, callback: Box<dyn FnOnce()>) -> MemoryTimeoutHandle {
+        TIMEOUTS.with(|timeouts| {
+            let mut timeouts = timeouts.borrow_mut();
+            let id = timeouts.next_id;
+            timeouts.next_id += 1;
+            let due = timeouts.now + duration;
+            timeouts.pending.push(PendingTimeout { id, due, callback });
+            MemoryTimeoutHandle { id }
+        })
+    }
+
+    fn spawn_local(future: Pin<Box<dyn Future<Output = ()>>>) {
+        EXECUTOR.with(|(_, spawner)| {
+            spawner
+                .spawn_local(future)
+                .expect("the executor of the in-memory backend is never shut down")
+        });
+    }
+}
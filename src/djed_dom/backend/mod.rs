@@ -0,0 +1,233 @@
+//! The DOM backends which virtual DOM trees are applied to.
+//!
+//! Every operation the diff engine performs on the DOM goes through the [`DomBackend`] trait.
+//! When compiling to WebAssembly the [`WebSysBackend`] applies trees to the browser DOM,
+//! otherwise the [`MemoryBackend`] applies them to an in-memory tree, so that components can
+//! be mounted, updated and inspected natively with `cargo test`.
+//!
+//! The backend is a compile-time switch on `target_arch`, not a parameter: an application
+//! can't choose it or use both. [`Backend`] and the [`Node`], [`Element`] and [`Text`] types
+//! are aliases of the ones of the backend of the target, so they are `web_sys` types on
+//! `wasm32` and [`MemoryNode`]s elsewhere. Code which needs the `web_sys` types, like
+//! `NodeRef::cast`, only works in the browser.
+//!
+//! The backend also sets the timeouts and spawns the futures of callbacks. The in-memory
+//! ones only run when the tests advance the time with [`MemoryBackend::advance_time`] and
+//! poll the futures with [`MemoryBackend::run_futures`].
+
+mod memory;
+mod web;
+
+pub use memory::{
+    MemoryBackend, MemoryListenerHandle, MemoryNode, MemoryTimeoutHandle, MemoryValue,
+};
+pub use web::WebSysBackend;
+
+use crate::djed::listener::{ChangeData, InputData};
+use gloo::events::EventListenerOptions;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+/// The backend used to apply virtual DOM trees.
+#[cfg(target_arch = "wasm32")]
+pub type Backend = WebSysBackend;
+
+/// The backend used to apply virtual DOM trees.
+#[cfg(not(target_arch = "wasm32"))]
+pub type Backend = MemoryBackend;
+
+/// A node of the DOM used by the [`Backend`].
+pub type Node = <Backend as DomBackend>::Node;
+
+/// An element of the DOM used by the [`Backend`].
+pub type Element = <Backend as DomBackend>::Element;
+
+/// A text node of the DOM used by the [`Backend`].
+pub type Text = <Backend as DomBackend>::Text;
+
+/// A value of a DOM property set by the [`Backend`].
+pub type PropertyValue = <Backend as DomBackend>::Value;
+
+/// A handle to an event listener attached by the [`Backend`].
+pub type ListenerHandle = <Backend as DomBackend>::ListenerHandle;
+
+/// A handle to a timeout set by the [`Backend`].
+pub type TimeoutHandle = <Backend as DomBackend>::TimeoutHandle;
+
+/// Converts an element or a text node of the [`Backend`] to a node. Natively they already
+/// are the same type.
+pub(crate) fn into_node(node: impl Into<Node>) -> Node {
+    node.into()
+}
+
+/// The operations on the DOM which are needed to apply a virtual DOM tree.
+pub trait DomBackend {
+    /// Any node of the DOM.
+    type Node: Clone + PartialEq + fmt::Debug;
+    /// An element of the DOM.
+    type Element: Clone + PartialEq + fmt::Debug + Into<Self::Node>;
+    /// A text node of the DOM.
+    type Text: Clone + fmt::Debug + Into<Self::Node>;
+    /// A value of a DOM property.
+    type Value: Clone
+        + PartialEq
+        + fmt::Debug
+        + From<bool>
+        + From<i32>
+        + From<f64>
+        + From<String>
+        + for<'a> From<&'a str>;
+    /// A handle which detaches its event listener when it is dropped.
+    type ListenerHandle;
+    /// A handle which cancels its timeout when it is dropped.
+    type TimeoutHandle;
+
+    /// Returns the root element of the document, the `<html>` element.
+    fn document_element() -> Self::Element;
+
+    /// Returns the `<body>` element of the document.
+    fn body() -> Self::Element;
+
+    /// Creates an element with the `tag` in the optional `namespace`.
+    fn create_element(tag: &str, namespace: Option<&str>) -> Self::Element;
+
+    /// Creates a text node with the `text`.
+    fn create_text(text: &str) -> Self::Text;
+
+    /// Returns the namespace of the element.
+    fn namespace_uri(element: &Self::Element) -> Option<String>;
+
+    /// Returns the tag of the element.
+    fn tag_name(element: &Self::Element) -> String;
+
+    /// Returns the name of the node, used for reporting.
+    fn node_name(node: &Self::Node) -> String;
+
+    /// Casts a node to an element.
+    fn as_element(node: &Self::Node) -> Option<Self::Element>;
+
+    /// Casts a node to a text node.
+    fn as_text(node: &Self::Node) -> Option<Self::Text>;
+
+    /// Inserts the `node` in `parent` before `next_sibling`, or appends it if there is no
+    /// next sibling. The node is moved if it is already in the DOM.
+    fn insert_before(parent: &Self::Element, node: &Self::Node, next_sibling: Option<&Self::Node>);
+
+    /// Removes the `node` from `parent`. Returns `false` if the node is not a child of `parent`.
+    fn remove_child(parent: &Self::Element, node: &Self::Node) -> bool;
+
+    /// Returns the first child of the element.
+    fn first_child(element: &Self::Element) -> Option<Self::Node>;
+
+    /// Returns the last child of the element.
+    fn last_child(element: &Self::Element) -> Option<Self::Node>;
+
+    /// Returns the next sibling of the node.
+    fn next_sibling(node: &Self::Node) -> Option<Self::Node>;
+
+    /// Returns the parent element of the node.
+    fn parent_element(node: &Self::Node) -> Option<Self::Element>;
+
+    /// Sets an attribute of the element.
+    fn set_attribute(element: &Self::Element, name: &str, value: &str);
+
+    /// Removes an attribute of the element.
+    fn remove_attribute(element: &Self::Element, name: &str);
+
+    /// Returns the names of the attributes of the element.
+    fn attribute_names(element: &Self::Element) -> Vec<String>;
+
+    /// Sets an inline style property of the element, with the `!important` priority if
+    /// `important` is set.
+    fn set_style(element: &Self::Element, property: &str, value: &str, important: bool);
+
+    /// Removes an inline style property of the element.
+    fn remove_style(element: &Self::Element, property: &str);
+
+    /// Sets a property of the element, like `selectedIndex` or a property of a custom element.
+    fn set_property(element: &Self::Element, name: &str, value: &Self::Value);
+
+    /// Resets a property of the element to `undefined`.
+    fn remove_property(element: &Self::Element, name: &str);
+
+    /// Returns the content of the text node.
+    fn text(text: &Self::Text) -> String;
+
+    /// Sets the content of the text node.
+    fn set_text(text: &Self::Text, value: &str);
+
+    /// Splits the text node after `prefix`, which has to be the beginning of its content.
+    /// The remaining content is moved to a new text node inserted after it.
+    fn split_text(text: &Self::Text, prefix: &str);
+
+    /// Replaces the children of the element with the nodes parsed from the `html` markup.
+    fn set_inner_html(element: &Self::Element, html: &str);
+
+    /// Returns the `value` of an `<input>`, a `<textarea>` or a `<select>`.
+    fn value(element: &Self::Element) -> Option<String>;
+
+    /// Sets the `value` of an `<input>`, a `<textarea>` or a `<select>`.
+    fn set_value(element: &Self::Element, value: &str);
+
+    /// Sets the `checked` state of an `<input>`.
+    fn set_checked(element: &Self::Element, checked: bool);
+
+    /// Sets the selectedness of an `<option>`.
+    fn set_selected(element: &Self::Element, selected: bool);
+
+    /// Returns the values of the selected options of a `<select>`.
+    fn selected_values(element: &Self::Element) -> Vec<String>;
+
+    /// Selects the options of a `<select>` whose value is one of `values`, and deselects the
+    /// others.
+    fn set_selected_values(element: &Self::Element, values: &[String]);
+
+    /// Sets the `type` of an `<input>` or a `<button>`.
+    fn set_kind(element: &Self::Element, kind: &str);
+
+    /// Attaches a listener for the `event_type` events of the element.
+    fn add_listener(
+        element: &Self::Element,
+        event_type: &'static str,
+        options: EventListenerOptions,
+        listener: Box<dyn Fn(web_sys::Event)>,
+    ) -> Self::ListenerHandle;
+
+    /// Returns the node which the event was dispatched to.
+    fn event_target(event: &web_sys::Event) -> Option<Self::Node>;
+
+    /// Returns the key of a keyboard event, like `Enter` or `a`.
+    fn event_key(event: &web_sys::KeyboardEvent) -> String;
+
+    /// Prevents the default action of the event.
+    fn prevent_default(event: &web_sys::Event);
+
+    /// Stops the propagation of the event to the listeners of the next nodes.
+    fn stop_propagation(event: &web_sys::Event);
+
+    /// Returns another handle to the event, which is passed to several listeners.
+    fn share_event(event: &web_sys::Event) -> web_sys::Event;
+
+    /// Returns `true` if a listener stopped the propagation of the event.
+    fn propagation_stopped(event: &web_sys::Event) -> bool;
+
+    /// Returns the key of the delegated listeners of the element.
+    fn delegation_key(element: &Self::Element) -> Option<usize>;
+
+    /// Sets the key of the delegated listeners of the element.
+    fn set_delegation_key(element: &Self::Element, key: Option<usize>);
+
+    /// Reads the data of an `input` event from its target.
+    fn input_data(element: &Self::Element) -> InputData;
+
+    /// Reads the data of a `change` event from its target.
+    fn change_data(element: &Self::Element) -> ChangeData;
+
+    /// Calls `callback` once `duration` elapsed, unless the returned handle was dropped.
+    fn set_timeout(duration: Duration, callback: Box<dyn FnOnce()>) -> Self::TimeoutHandle;
+
+    /// Runs the future on the current thread until it completes.
+    fn spawn_local(future: Pin<Box<dyn Future<Output = ()>>>);
+}
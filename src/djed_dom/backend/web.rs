@@ -0,0 +1,352 @@
+use super::DomBackend;
+use crate::djed::listener::{ChangeData, InputData};
+use crate::utils::document;
+use gloo::events::{EventListener, EventListenerOptions};
+use gloo::timers::callback::Timeout;
+use js_sys::Reflect;
+use std::convert::TryFrom;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{
+    CssStyleDeclaration, Element, Event, EventTarget, FileList, HtmlButtonElement as ButtonElement,
+    HtmlInputElement as InputElement, HtmlOptionElement as OptionElement, KeyboardEvent,
+    HtmlSelectElement as SelectElement,
+    HtmlTextAreaElement as TextAreaElement, Node, Text,
+};
+
+// The expando property of elements which stores the key of their delegated listeners
+const DELEGATION_KEY: &str = "__djedDelegationKey";
+
+/// The backend which applies virtual DOM trees to the browser DOM.
+#[derive(Debug)]
+pub struct WebSysBackend;
+
+impl DomBackend for WebSysBackend {
+    type Node = Node;
+    type Element = Element;
+    type Text = Text;
+    type Value = JsValue;
+    type ListenerHandle = EventListener;
+    type TimeoutHandle = Timeout;
+
+    fn document_element() -> Element {
+        document()
+            .query_selector("html")
+            .expect("can't get html node for rendering")
+            .expect("can't unwrap html node")
+    }
+
+    fn body() -> Element {
+        document()
+            .query_selector("body")
+            .expect("can't get body node for rendering")
+            .expect("can't unwrap body node")
+    }
+
+    fn create_element(tag: &str, namespace: Option<&str>) -> Element {
+        match namespace {
+            Some(namespace) => document()
+                .create_element_ns(Some(namespace), tag)
+                .expect("can't create namespaced element for vtag"),
+            None => document()
+                .create_element(tag)
+                .expect("can't create element for vtag"),
+        }
+    }
+
+    fn create_text(text: &str) -> Text {
+        document().create_text_node(text)
+    }
+
+    fn namespace_uri(element: &Element) -> Option<String> {
+        element.namespace_uri()
+    }
+
+    fn tag_name(element: &Element) -> String {
+        element.tag_name()
+    }
+
+    fn node_name(node: &Node) -> String {
+        node.node_name()
+    }
+
+    fn as_element(node: &Node) -> Option<Element> {
+        node.dyn_ref::<Element>().cloned()
+    }
+
+    fn as_text(node: &Node) -> Option<Text> {
+        node.dyn_ref::<Text>().cloned()
+    }
+
+    fn insert_before(parent: &Element, node: &Node, next_sibling: Option<&Node>) {
+        match next_sibling {
+            Some(next_sibling) => parent
+                .insert_before(node, Some(next_sibling))
+                .expect("failed to insert tag before next sibling"),
+            None => parent.append_child(node).expect("failed to append child"),
+        };
+    }
+
+    fn remove_child(parent: &Element, node: &Node) -> bool {
+        parent.remove_child(node).is_ok()
+    }
+
+    fn first_child(element: &Element) -> Option<Node> {
+        element.first_child()
+    }
+
+    fn last_child(element: &Element) -> Option<Node> {
+        element.last_child()
+    }
+
+    fn next_sibling(node: &Node) -> Option<Node> {
+        node.next_sibling()
+    }
+
+    fn parent_element(node: &Node) -> Option<Element> {
+        node.parent_element()
+    }
+
+    fn set_attribute(element: &Element, name: &str, value: &str) {
+        element
+            .set_attribute(name, value)
+            .expect("invalid attribute key");
+    }
+
+    fn remove_attribute(element: &Element, name: &str) {
+        element
+            .remove_attribute(name)
+            .expect("could not remove attribute");
+    }
+
+    fn attribute_names(element: &Element) -> Vec<String> {
+        element
+            .get_attribute_names()
+            .iter()
+            .filter_map(|name| name.as_string())
+            .collect()
+    }
+
+    fn text(text: &Text) -> String {
+        text.data()
+    }
+
+    fn set_text(text: &Text, value: &str) {
+        text.set_node_value(Some(value));
+    }
+
+    fn split_text(text: &Text, prefix: &str) {
+        // Offsets in the DOM are counted in UTF-16 code units
+        let offset = prefix.encode_utf16().count() as u32;
+        text.split_text(offset)
+            .expect("can't split text node for hydration");
+    }
+
+    fn set_style(element: &Element, property: &str, value: &str, important: bool) {
+        let priority = if important { "important" } else { "" };
+        style(element)
+            .set_property_with_priority(property, value, priority)
+            .expect("could not set style property");
+    }
+
+    fn remove_style(element: &Element, property: &str) {
+        style(element)
+            .remove_property(property)
+            .expect("could not remove style property");
+    }
+
+    fn set_property(element: &Element, name: &str, value: &JsValue) {
+        Reflect::set(element, &JsValue::from_str(name), value).expect("could not set property");
+    }
+
+    fn remove_property(element: &Element, name: &str) {
+        Reflect::set(element, &JsValue::from_str(name), &JsValue::UNDEFINED)
+            .expect("could not remove property");
+    }
+
+    fn set_inner_html(element: &Element, html: &str) {
+        element.set_inner_html(html);
+    }
+
+    fn value(element: &Element) -> Option<String> {
+        if let Some(input) = element.dyn_ref::<InputElement>() {
+            Some(input.value())
+        } else if let Some(select) = element.dyn_ref::<SelectElement>() {
+            Some(select.value())
+        } else {
+            element
+                .dyn_ref::<TextAreaElement>()
+                .map(|tae| tae.value())
+        }
+    }
+
+    fn set_value(element: &Element, value: &str) {
+        if let Some(input) = element.dyn_ref::<InputElement>() {
+            input.set_value(value);
+        } else if let Some(tae) = element.dyn_ref::<TextAreaElement>() {
+            tae.set_value(value);
+        } else if let Some(select) = element.dyn_ref::<SelectElement>() {
+            select.set_value(value);
+        }
+    }
+
+    fn set_checked(element: &Element, checked: bool) {
+        if let Some(input) = element.dyn_ref::<InputElement>() {
+            input.set_checked(checked);
+        }
+    }
+
+    fn set_selected(element: &Element, selected: bool) {
+        if let Some(option) = element.dyn_ref::<OptionElement>() {
+            option.set_selected(selected);
+        }
+    }
+
+    fn selected_values(element: &Element) -> Vec<String> {
+        let options = match element.dyn_ref::<SelectElement>() {
+            Some(select) => select.selected_options(),
+            None => return Vec::new(),
+        };
+        (0..options.length())
+            .filter_map(|index| options.item(index))
+            .filter_map(|option| option.dyn_into::<OptionElement>().ok())
+            .map(|option| option.value())
+            .collect()
+    }
+
+    fn set_selected_values(element: &Element, values: &[String]) {
+        let options = match element.dyn_ref::<SelectElement>() {
+            Some(select) => select.options(),
+            None => return,
+        };
+        for index in 0..options.length() {
+            let option = options
+                .get_with_index(index)
+                .and_then(|option| option.dyn_into::<OptionElement>().ok());
+            if let Some(option) = option {
+                option.set_selected(values.contains(&option.value()));
+            }
+        }
+    }
+
+    fn set_kind(element: &Element, kind: &str) {
+        if let Some(input) = element.dyn_ref::<InputElement>() {
+            input.set_type(kind);
+        } else if let Some(button) = element.dyn_ref::<ButtonElement>() {
+            button.set_type(kind);
+        }
+    }
+
+    fn add_listener(
+        element: &Element,
+        event_type: &'static str,
+        options: EventListenerOptions,
+        listener: Box<dyn Fn(Event)>,
+    ) -> EventListener {
+        let target = EventTarget::from(element.clone());
+        EventListener::new_with_options(&target, event_type, options, move |event| {
+            listener(event.clone())
+        })
+    }
+
+    fn event_target(event: &Event) -> Option<Node> {
+        event.target()?.dyn_into().ok()
+    }
+
+    fn event_key(event: &KeyboardEvent) -> String {
+        event.key()
+    }
+
+    fn prevent_default(event: &Event) {
+        event.prevent_default();
+    }
+
+    fn stop_propagation(event: &Event) {
+        event.stop_propagation();
+    }
+
+    fn share_event(event: &Event) -> Event {
+        event.clone()
+    }
+
+    fn propagation_stopped(event: &Event) -> bool {
+        event.cancel_bubble()
+    }
+
+    fn delegation_key(element: &Element) -> Option<usize> {
+        Reflect::get(element, &JsValue::from_str(DELEGATION_KEY))
+            .ok()?
+            .as_f64()
+            .map(|key| key as usize)
+    }
+
+    fn set_delegation_key(element: &Element, key: Option<usize>) {
+        let key = key.map_or(JsValue::UNDEFINED, |key| JsValue::from(key as f64));
+        Reflect::set(element, &JsValue::from_str(DELEGATION_KEY), &key)
+            .expect("could not set the delegation key");
+    }
+
+    fn input_data(this: &Element) -> InputData {
+        // Normally only InputElement or TextAreaElement can have an oninput event listener. In
+        // practice though any element with `contenteditable=true` may generate such events,
+        // therefore here we fall back to just returning the text content of the node.
+        // See https://developer.mozilla.org/en-US/docs/Web/API/HTMLElement/input_event.
+        let (v1, v2) = {
+            (
+                this.dyn_ref().map(|input: &InputElement| input.value()),
+                this.dyn_ref().map(|input: &TextAreaElement| input.value()),
+            )
+        };
+        let v3 = this.text_content();
+        let value = v1.or(v2).or(v3)
+            .expect("only an InputElement or TextAreaElement or an element with contenteditable=true can have an oninput event listener");
+        InputData { value }
+    }
+
+    fn change_data(this: &Element) -> ChangeData {
+        match this.node_name().as_ref() {
+            "INPUT" => {
+                let input = this.dyn_ref::<InputElement>().unwrap();
+                let is_file = input
+                    .get_attribute("type")
+                    .map(|value| value.eq_ignore_ascii_case("file"))
+                    .unwrap_or(false);
+                if is_file {
+                    let files: FileList =  input.files().unwrap();
+                    ChangeData::Files(files)
+                } else {
+                    ChangeData::Value(input.value())
+                }
+            }
+            "TEXTAREA" => {
+                let tae = this.dyn_ref::<TextAreaElement>().unwrap();
+                ChangeData::Value(tae.value())
+            }
+            "SELECT" => {
+                let se = this.dyn_ref::<SelectElement>().unwrap().clone();
+                ChangeData::Select(se)
+            }
+            _ => {
+                unimplemented!("only an InputElement, TextAreaElement or SelectElement can have an onchange event listener");
+            }
+        }
+    }
+
+    fn set_timeout(duration: Duration, callback: Box<dyn FnOnce()>) -> Timeout {
+        let ms = u32::try_from(duration.as_millis()).unwrap_or(u32::MAX);
+        Timeout::new(ms, callback)
+    }
+
+    fn spawn_local(future: Pin<Box<dyn Future<Output = ()>>>) {
+        wasm_bindgen_futures::spawn_local(future);
+    }
+}
+
+// Both HTML and SVG elements have a `style`, but they don't share an interface for it
+fn style(element: &Element) -> CssStyleDeclaration {
+    Reflect::get(element, &JsValue::from_str("style"))
+        .expect("element has no style")
+        .unchecked_into()
+}
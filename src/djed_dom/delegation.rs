@@ -0,0 +1,328 @@
+//! Delegation of the events of an application to its mount element.
+//!
+//! Instead of attaching a listener to every element, one listener per event type is installed
+//! on the mount element, and the portal hosts outside of it. When an event reaches it, the
+//! handlers registered for the elements between the target of the event and the mount element
+//! are called from the innermost to the outermost, until one of them stops the propagation.
+
+use super::backend::{into_node, Backend, DomBackend, Element, ListenerHandle};
+use super::{Listener, ListenerOptions};
+use slab::Slab;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+/// A handler which converts an event for the element it is registered for.
+pub type Handler = Rc<dyn Fn(&Element, web_sys::Event)>;
+
+/// A handler of an event type.
+pub type DelegatedHandler = (&'static str, Handler);
+
+/// Events which don't bubble. They are caught in the capture phase and only handled by the
+/// handlers of their target.
+const NON_BUBBLING_EVENTS: &[&str] = &[
+    "abort",
+    "blur",
+    "canplay",
+    "canplaythrough",
+    "durationchange",
+    "emptied",
+    "ended",
+    "error",
+    "focus",
+    "invalid",
+    "load",
+    "loadeddata",
+    "loadedmetadata",
+    "loadstart",
+    "mouseenter",
+    "mouseleave",
+    "pause",
+    "play",
+    "playing",
+    "pointerenter",
+    "pointerleave",
+    "progress",
+    "ratechange",
+    "scroll",
+    "seeked",
+    "seeking",
+    "stalled",
+    "suspend",
+    "timeupdate",
+    "toggle",
+    "volumechange",
+    "waiting",
+];
+
+struct Registration {
+    element: Element,
+    handlers: Vec<DelegatedHandler>,
+}
+
+thread_local! {
+    static REGISTRATIONS: RefCell<Slab<Registration>> = const { RefCell::new(Slab::new()) };
+}
+
+/// The listeners of an application which delegates its events to its mount element.
+pub(crate) struct EventDelegation {
+    roots: RefCell<Vec<Element>>,
+    listeners: RefCell<HashMap<&'static str, Vec<ListenerHandle>>>,
+}
+
+impl fmt::Debug for EventDelegation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EventDelegation")
+    }
+}
+
+impl EventDelegation {
+    /// Delegates the events of the elements in `root` to it.
+    pub(crate) fn new(root: Element) -> Rc<Self> {
+        Rc::new(EventDelegation {
+            roots: RefCell::new(vec![root]),
+            listeners: RefCell::new(HashMap::new()),
+        })
+    }
+
+    /// Delegates the events of the elements in `host` to it as well, unless it is already
+    /// inside of a root.
+    pub(crate) fn add_root(&self, host: &Element) {
+        let node = into_node(host.clone());
+        let contained = std::iter::successors(Some(node), |node| {
+            Backend::parent_element(node).map(into_node)
+        })
+        .filter_map(|node| Backend::as_element(&node))
+        .any(|element| self.roots.borrow().contains(&element));
+        if contained {
+            return;
+        }
+
+        self.roots.borrow_mut().push(host.clone());
+        let mut listeners = self.listeners.borrow_mut();
+        for (event_type, handles) in listeners.iter_mut() {
+            handles.push(listen(host, event_type));
+        }
+    }
+
+    /// Installs the listeners for `event_type` on the roots if they aren't yet.
+    pub(crate) fn listen(&self, event_type: &'static str) {
+        let mut listeners = self.listeners.borrow_mut();
+        if !listeners.contains_key(event_type) {
+            let handles = self
+                .roots
+                .borrow()
+                .iter()
+                .map(|root| listen(root, event_type))
+                .collect();
+            listeners.insert(event_type, handles);
+        }
+    }
+}
+
+fn listen(root: &Element, event_type: &'static str) -> ListenerHandle {
+    let bubbles = !NON_BUBBLING_EVENTS.contains(&event_type);
+    let options = ListenerOptions {
+        capture: !bubbles,
+        ..ListenerOptions::default()
+    };
+
+    let target = root.clone();
+    let options = options.to_event_listener_options(event_type);
+    let listener = move |event: web_sys::Event| {
+        let mut node = Backend::event_target(&event);
+        while let Some(current) = node {
+            if let Some(element) = Backend::as_element(&current) {
+                for handler in handlers(&element, event_type) {
+                    handler(&element, Backend::share_event(&event));
+                }
+                if element == target {
+                    break;
+                }
+            }
+            if !bubbles || Backend::propagation_stopped(&event) {
+                break;
+            }
+            node = Backend::parent_element(&current).map(into_node);
+        }
+    };
+    Backend::add_listener(root, event_type, options, Box::new(listener))
+}
+
+fn handlers(element: &Element, event_type: &str) -> Vec<Handler> {
+    let key = match Backend::delegation_key(element) {
+        Some(key) => key,
+        None => return Vec::new(),
+    };
+    REGISTRATIONS.with(|registrations| {
+        match registrations.borrow().get(key) {
+            // The key of an element outlives its registration
+            Some(registration) if &registration.element == element => registration
+                .handlers
+                .iter()
+                .filter(|(kind, _)| *kind == event_type)
+                .map(|(_, handler)| handler.clone())
+                .collect(),
+            _ => Vec::new(),
+        }
+    })
+}
+
+/// The handlers registered for an element, which are unregistered when it is dropped.
+#[derive(Debug)]
+pub(crate) struct DelegatedListeners {
+    key: usize,
+    listeners: Vec<Rc<dyn Listener>>,
+}
+
+impl DelegatedListeners {
+    /// Returns `true` if the handlers are registered for the `element` and the `listeners`
+    /// are the same as the ones they were created from.
+    pub(crate) fn handles(&self, element: &Element, listeners: &[Rc<dyn Listener>]) -> bool {
+        self.listeners.len() == listeners.len()
+            && self
+                .listeners
+                .iter()
+                .zip(listeners)
+                .all(|(registered, listener)| listener.same_as(registered.as_ref()))
+            && self.is_registered_for(element)
+    }
+
+    /// Registers the `handlers` of the `listeners` of the `element`, reusing the registration
+    /// of its previous handlers if any.
+    pub(crate) fn register(
+        element: &Element,
+        listeners: Vec<Rc<dyn Listener>>,
+        handlers: Vec<DelegatedHandler>,
+        previous: Option<DelegatedListeners>,
+    ) -> Option<Self> {
+        let previous = previous.filter(|previous| previous.is_registered_for(element));
+        if handlers.is_empty() {
+            return None;
+        }
+        if let Some(mut previous) = previous {
+            // The replaced handlers are dropped once the registrations aren't borrowed anymore
+            let _replaced = REGISTRATIONS.with(|registrations| {
+                let registration = &mut registrations.borrow_mut()[previous.key];
+                std::mem::replace(&mut registration.handlers, handlers)
+            });
+            previous.listeners = listeners;
+            return Some(previous);
+        }
+
+        let key = REGISTRATIONS.with(|registrations| {
+            registrations.borrow_mut().insert(Registration {
+                element: element.clone(),
+                handlers,
+            })
+        });
+        Backend::set_delegation_key(element, Some(key));
+        Some(DelegatedListeners { key, listeners })
+    }
+
+    fn is_registered_for(&self, element: &Element) -> bool {
+        REGISTRATIONS.with(|registrations| {
+            registrations
+                .borrow()
+                .get(self.key)
+                .is_some_and(|registration| &registration.element == element)
+        })
+    }
+}
+
+impl Drop for DelegatedListeners {
+    fn drop(&mut self) {
+        let _removed = REGISTRATIONS.with(|registrations| {
+            let mut registrations = registrations.borrow_mut();
+            if registrations.contains(self.key) {
+                Some(registrations.remove(self.key))
+            } else {
+                None
+            }
+        });
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use crate::djed::listener::onclick;
+    use crate::djed::{Component, ComponentLink, Html};
+    use crate::djed_dom::{VTag, VText};
+    use crate::testing::TestRenderer;
+    use std::rc::Rc;
+
+    enum Msg {
+        Stop,
+        Clicked(&'static str),
+    }
+
+    /// A button in a panel which both log their clicks. The button stops the propagation of
+    /// the clicks once it received `Msg::Stop`.
+    struct Panel {
+        link: ComponentLink<Self>,
+        stop: bool,
+        clicks: Vec<&'static str>,
+    }
+
+    impl Component for Panel {
+        type State = Msg;
+        type Props = ();
+
+        fn create(_: (), link: ComponentLink<Self>) -> Self {
+            Panel {
+                link,
+                stop: false,
+                clicks: Vec::new(),
+            }
+        }
+
+        fn update(&mut self, msg: Msg) -> bool {
+            match msg {
+                Msg::Stop => self.stop = true,
+                Msg::Clicked(name) => self.clicks.push(name),
+            }
+            true
+        }
+
+        fn change(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn view(&self) -> Html {
+            let mut inner = onclick::Wrapper::new(self.link.callback(|_| Msg::Clicked("button")));
+            if self.stop {
+                inner = inner.stop_propagation();
+            }
+            let mut button = VTag::new("button");
+            button.add_listener(Rc::new(inner));
+            let outer = onclick::Wrapper::new(self.link.callback(|_| Msg::Clicked("panel")));
+            let mut panel = VTag::new("div");
+            panel.add_listener(Rc::new(outer));
+            panel.add_child(button.into());
+            panel.add_child(VText::new(self.clicks.join(" ")).into());
+            panel.into()
+        }
+    }
+
+    fn stop_propagation(renderer: TestRenderer<Panel>) {
+        let button = renderer.find_by_tag("button").remove(0);
+        renderer.click(&button);
+        assert_eq!(renderer.root().text_content(), "button panel");
+
+        renderer.link().send_message(Msg::Stop);
+        renderer.flush();
+        renderer.click(&button);
+        assert_eq!(renderer.root().text_content(), "button panel button");
+    }
+
+    #[test]
+    fn delegated_handler_stops_propagation() {
+        stop_propagation(TestRenderer::mount_with_event_delegation());
+    }
+
+    #[test]
+    fn attached_listener_stops_propagation() {
+        stop_propagation(TestRenderer::mount());
+    }
+}
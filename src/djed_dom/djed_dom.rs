@@ -1,12 +1,13 @@
 use crate::djed::{Component, ComponentLink, NodeRef, Scope};
-use crate::utils::document;
-use web_sys::Element;
+use super::backend::{into_node, Backend, DomBackend, Element};
 
 /// An instance of an application.
 #[derive(Debug)]
 pub struct Render<COMP: Component> {
     /// `Scope` holder
     scope: Scope<COMP>,
+    /// Whether the events are delegated to the mount element
+    delegate_events: bool,
 }
 
 impl<COMP> Default for Render<COMP>
@@ -30,7 +31,7 @@ where
     /// use the `mount_with_props` method.
     pub fn mount(self, element: Element) -> ComponentLink<COMP> {
         clear_element(&element);
-        self.scope.mount_in_place(
+        self.root_scope(&element).mount_in_place(
             element,
             NodeRef::default(),
             None,
@@ -39,14 +40,26 @@ where
         )
     }
 
+    /// Alternative to `mount` which adopts the child nodes the `element` already contains,
+    /// for example when they were pre-rendered with `render_to_string`. Listeners are
+    /// attached to the existing nodes and mismatches are reported through `log`. If you would
+    /// like to pass props, use the `hydrate_with_props` method.
+    pub fn hydrate(self, element: Element) -> ComponentLink<COMP> {
+        self.root_scope(&element)
+            .hydrate_in_place(element, COMP::Props::default())
+    }
+
+    /// Renders the component into an HTML string without a browser `Document`. Use this
+    /// method to pre-render pages on the server. If you would like to pass props, use the
+    /// `render_to_string_with_props` method.
+    pub fn render_to_string(self) -> String {
+        self.render_to_string_with_props(COMP::Props::default())
+    }
+
     /// Alias to `mount("body", ...)`.
     pub fn mount_to_body(self) -> ComponentLink<COMP> {
         // Bootstrap the component for `Window` environment only (not for `Worker`)
-        let element = document()
-            .query_selector("body")
-            .expect("can't get body node for rendering")
-            .expect("can't unwrap body node");
-        self.mount(element)
+        self.mount(Backend::body())
     }
 
     /// Alternative to `mount` which replaces the body element with a component which has a body
@@ -54,18 +67,12 @@ where
     /// need to manipulate the body element. For example, adding/removing app-wide
     /// CSS classes of the body element.
     pub fn mount_as_body(self) -> ComponentLink<COMP> {
-        let html_element = document()
-            .query_selector("html")
-            .expect("can't get html node for rendering")
-            .expect("can't unwrap html node");
-        let body_element = document()
-            .query_selector("body")
-            .expect("can't get body node for rendering")
-            .expect("can't unwrap body node");
-        html_element
-            .remove_child(&body_element)
-            .expect("can't remove body child");
-        self.scope.mount_in_place(
+        let html_element = Backend::document_element();
+        let body_element = Backend::body();
+        if !Backend::remove_child(&html_element, &into_node(body_element)) {
+            panic!("can't remove body child");
+        }
+        self.root_scope(&html_element).mount_in_place(
             html_element,
             NodeRef::default(),
             None,
@@ -82,7 +89,30 @@ where
     /// Creates a new `App` with a component in a context.
     pub fn new() -> Self {
         let scope = Scope::new(None);
-        Render { scope }
+        Render {
+            scope,
+            delegate_events: false,
+        }
+    }
+
+    /// Delegates the events of the application to the element it is mounted to. Instead of
+    /// attaching listeners to every element on every render, one listener per event type is
+    /// installed on the mount element, and the handlers of the elements between the target of
+    /// an event and the mount element are called until one of them stops its propagation.
+    ///
+    /// Events which don't bubble, like `focus` or `mouseenter`, only reach the handlers of
+    /// their target. Portal hosts outside of the mount element get their own listeners.
+    pub fn with_event_delegation(mut self) -> Self {
+        self.delegate_events = true;
+        self
+    }
+
+    fn root_scope(self, element: &Element) -> Scope<COMP> {
+        if self.delegate_events {
+            self.scope.delegate_events_to(element)
+        } else {
+            self.scope
+        }
     }
 
     /// The main entry point of a Yew program which also allows passing properties. It works
@@ -95,18 +125,34 @@ where
         props: COMP::Props,
     ) -> ComponentLink<COMP> {
         clear_element(&element);
-        self.scope
-            .mount_in_place(element, NodeRef::default(), None, NodeRef::default(), props)
+        self.root_scope(&element).mount_in_place(
+            element,
+            NodeRef::default(),
+            None,
+            NodeRef::default(),
+            props,
+        )
+    }
+
+    /// Alternative to `mount_with_props` which adopts the child nodes the `element` already
+    /// contains, for example when they were pre-rendered with `render_to_string_with_props`.
+    pub fn hydrate_with_props(self, element: Element, props: COMP::Props) -> ComponentLink<COMP> {
+        self.root_scope(&element).hydrate_in_place(element, props)
+    }
+
+    /// Renders the component with properties into an HTML string without a browser `Document`.
+    /// The `create` and `view` methods of the component and its children are called, but
+    /// nothing is mounted and `rendered` is never called.
+    pub fn render_to_string_with_props(self, props: COMP::Props) -> String {
+        let mut html = String::new();
+        self.scope.render_html(props, false, &mut html);
+        html
     }
 
     /// Alias to `mount_with_props("body", ...)`.
     pub fn mount_to_body_with_props(self, props: COMP::Props) -> ComponentLink<COMP> {
         // Bootstrap the component for `Window` environment only (not for `Worker`)
-        let element = document()
-            .query_selector("body")
-            .expect("can't get body node for rendering")
-            .expect("can't unwrap body node");
-        self.mount_with_props(element, props)
+        self.mount_with_props(Backend::body(), props)
     }
 
     /// Alternative to `mount_with_props` which replaces the body element with a component which
@@ -114,18 +160,12 @@ where
     /// when you need to manipulate the body element. For example, adding/removing app-wide
     /// CSS classes of the body element.
     pub fn mount_as_body_with_props(self, props: COMP::Props) -> ComponentLink<COMP> {
-        let html_element = document()
-            .query_selector("html")
-            .expect("can't get html node for rendering")
-            .expect("can't unwrap html node");
-        let body_element = document()
-            .query_selector("body")
-            .expect("can't get body node for rendering")
-            .expect("can't unwrap body node");
-        html_element
-            .remove_child(&body_element)
-            .expect("can't remove body child");
-        self.scope.mount_in_place(
+        let html_element = Backend::document_element();
+        let body_element = Backend::body();
+        if !Backend::remove_child(&html_element, &into_node(body_element)) {
+            panic!("can't remove body child");
+        }
+        self.root_scope(&html_element).mount_in_place(
             html_element,
             NodeRef::default(),
             None,
@@ -137,12 +177,81 @@ where
 
 /// Removes anything from the given element.
 fn clear_element(element: &Element) {
-    while let Some(child) = element.last_child() {
-        element.remove_child(&child).expect("can't remove a child");
+    while let Some(child) = Backend::last_child(element) {
+        if !Backend::remove_child(element, &child) {
+            panic!("can't remove a child");
+        }
     }
 }
 
 /// Initializes yew framework. It should be called first.
 pub fn initialize() {
     std::panic::set_hook(Box::new(console_error_panic_hook::hook));
- }
\ No newline at end of file
+ }
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::djed::Html;
+    use crate::djed_dom::VTag;
+    use crate::scheduler::scheduler;
+
+    /// Renders a `<body>` with a class.
+    struct Page;
+
+    impl Component for Page {
+        type State = ();
+        type Props = ();
+
+        fn create(_: (), _: ComponentLink<Self>) -> Self {
+            Page
+        }
+
+        fn update(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn change(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn view(&self) -> Html {
+            let mut body = VTag::new("body");
+            body.add_attribute("class", &"page");
+            body.into()
+        }
+    }
+
+    #[test]
+    fn mount_to_the_in_memory_body() {
+        Render::<Page>::new().mount_to_body();
+        scheduler().run_until_idle();
+        assert_eq!(
+            Backend::document_element().outer_html(),
+            "<html><body><body class=\"page\"></body></body></html>"
+        );
+    }
+
+    #[test]
+    fn mount_as_the_in_memory_body() {
+        Render::<Page>::new().mount_as_body();
+        scheduler().run_until_idle();
+        assert_eq!(
+            Backend::document_element().outer_html(),
+            "<html><body class=\"page\"></body></html>"
+        );
+    }
+
+    #[test]
+    fn get_memory_node() {
+        assert!(NodeRef::default().cast::<web_sys::Element>().is_none());
+        let node_ref = NodeRef::new(into_node(Backend::body()));
+        assert_eq!(node_ref.get(), Some(Backend::body()));
+    }
+
+    #[test]
+    #[should_panic(expected = "can't be cast to web_sys types")]
+    fn cast_memory_node() {
+        let node_ref = NodeRef::new(into_node(Backend::body()));
+        node_ref.cast::<web_sys::Element>();
+    }
+}
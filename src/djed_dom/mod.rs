@@ -1,3 +1,5 @@
+pub mod backend;
+pub(crate) mod delegation;
 mod v_dom;
 mod djed_dom;
 #[doc(hidden)]
@@ -7,6 +9,10 @@ mod v_list;
 #[doc(hidden)]
 mod v_node;
 #[doc(hidden)]
+mod v_portal;
+#[doc(hidden)]
+mod v_raw;
+#[doc(hidden)]
 mod v_tag;
 #[doc(hidden)]
 mod v_text;
@@ -16,10 +22,13 @@ pub use v_text::VText;
 pub use v_tag::VTag;
 pub use v_node::VNode;
 pub use v_list::VList;
+pub use v_portal::VPortal;
+pub use v_raw::VRaw;
 pub use v_dom::{
-    Listener, Classes, Transformer, VDiff, insert_node,
-    Attributes, Listeners,Patch
+    Listener, ListenerOptions, Classes, Transformer, VDiff, VRender, insert_node,
+    Attributes, Listeners, Patch, Properties, Styles
 };
+pub(crate) use v_dom::{escape_attribute, escape_text, remove_unclaimed};
 pub use v_comp::{
     VComp,VChild
 };
@@ -1,12 +1,11 @@
-use super::{Transformer, VDiff};
+use super::{Transformer, VDiff, VRender};
 use super::v_node::VNode;
-use crate::djed::{AnyScope, Component, ComponentUpdate, NodeRef, Scope, Scoped};
-use crate::utils::document;
+use crate::djed::{AnyScope, Component, NodeRef, Scope, Scoped};
 use std::any::TypeId;
 use std::borrow::Borrow;
 use std::fmt;
 use std::ops::Deref;
-use web_sys::{Element, Node};
+use super::backend::{into_node, Backend, DomBackend, Element, Node};
 
 
 /// A virtual component.
@@ -100,7 +99,6 @@ impl VComp {
         }
     }
 
-    #[allow(unused)]
     pub(crate) fn root_vnode(&self) -> Option<impl Deref<Target = VNode> + '_> {
         self.scope.as_ref().and_then(|scope| scope.root_vnode())
     }
@@ -115,7 +113,16 @@ trait Mountable {
         parent: Element,
         next_sibling: NodeRef,
     ) -> Box<dyn Scoped>;
+    fn hydrate(
+        self: Box<Self>,
+        node_ref: NodeRef,
+        parent_scope: &AnyScope,
+        parent: Element,
+        next_sibling: NodeRef,
+        cursor: &mut Option<Node>,
+    ) -> Box<dyn Scoped>;
     fn reuse(self: Box<Self>, scope: &dyn Scoped, next_sibling: NodeRef);
+    fn render_html(&self, parent_scope: Option<&AnyScope>, svg: bool, html: &mut String);
 }
 
 struct PropsWrapper<COMP: Component> {
@@ -155,9 +162,27 @@ impl<COMP: Component> Mountable for PropsWrapper<COMP> {
         Box::new(scope)
     }
 
+    fn hydrate(
+        self: Box<Self>,
+        node_ref: NodeRef,
+        parent_scope: &AnyScope,
+        parent: Element,
+        next_sibling: NodeRef,
+        cursor: &mut Option<Node>,
+    ) -> Box<dyn Scoped> {
+        let scope: Scope<COMP> = Scope::new(Some(parent_scope.clone()));
+        scope.hydrate_nodes(parent, next_sibling, node_ref, self.props, cursor);
+        Box::new(scope)
+    }
+
     fn reuse(self: Box<Self>, scope: &dyn Scoped, next_sibling: NodeRef) {
         let scope: Scope<COMP> = scope.to_any().downcast();
-        scope.update(ComponentUpdate::Properties(self.props, next_sibling), false);
+        scope.change(self.props, next_sibling);
+    }
+
+    fn render_html(&self, parent_scope: Option<&AnyScope>, svg: bool, html: &mut String) {
+        let scope: Scope<COMP> = Scope::new(parent_scope.cloned());
+        scope.render_html(self.props.clone(), svg, html);
     }
 }
 
@@ -190,7 +215,7 @@ impl VDiff for VComp {
             ancestor.detach(parent);
         }
 
-        let placeholder: Node = document().create_text_node("").into();
+        let placeholder = into_node(Backend::create_text(""));
         super::insert_node(&placeholder, parent, next_sibling.get());
         self.node_ref.set(Some(placeholder));
         let scope = mountable.mount(
@@ -202,6 +227,41 @@ impl VDiff for VComp {
         self.scope = Some(scope);
         self.node_ref.clone()
     }
+
+    /// Creates and renders the component right away, so that its view adopts the DOM
+    /// nodes under the cursor before the siblings of the component do.
+    fn hydrate(
+        &mut self,
+        parent_scope: &AnyScope,
+        parent: &Element,
+        next_sibling: NodeRef,
+        cursor: &mut Option<Node>,
+    ) -> NodeRef {
+        let mountable = self.props.take().expect("VComp has already been mounted");
+        let scope = mountable.hydrate(
+            self.node_ref.clone(),
+            parent_scope,
+            parent.to_owned(),
+            next_sibling,
+            cursor,
+        );
+        self.scope = Some(scope);
+        self.node_ref.clone()
+    }
+}
+
+impl VRender for VComp {
+    /// Renders a component which is not mounted yet by creating it, otherwise writes
+    /// the last rendered view of the mounted component.
+    fn render_html(&self, parent_scope: Option<&AnyScope>, svg: bool, html: &mut String) {
+        if let Some(props) = &self.props {
+            props.render_html(parent_scope, svg, html);
+        } else if let Some(scope) = &self.scope {
+            if let Some(root) = scope.root_vnode() {
+                root.render_html(Some(&scope.to_any()), svg, html);
+            }
+        }
+    }
 }
 
 impl<T> Transformer<T, T> for VComp {
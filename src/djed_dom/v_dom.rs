@@ -1,18 +1,85 @@
-use web_sys::{Element, Node};
+use super::backend::{Backend, DomBackend, Element, ListenerHandle, Node, PropertyValue};
 use crate::djed::{AnyScope, NodeRef};
-use indexmap::set::IndexSet;
+use gloo::events::{EventListenerOptions, EventListenerPhase};
+use indexmap::{map::IndexMap, set::IndexSet};
+use std::any::Any;
 use std::collections::HashMap;
 use std::fmt;
 use std::rc::Rc;
-use gloo::events::EventListener;
+use super::delegation::DelegatedHandler;
 use super::VNode;
+use log::warn;
 /// The `Listener` trait is an universal implementation of an event listener
 /// which is used to bind Rust-listener to JS-listener (DOM).
 pub trait Listener {
     /// Returns the name of the event
     fn kind(&self) -> &'static str;
     /// Attaches a listener to the element.
-    fn attach(&self, element: &Element) -> EventListener;
+    fn attach(&self, element: &Element) -> ListenerHandle;
+    /// Returns the event type and the handler of the listener. An element calls its
+    /// listeners through their handler, so that a listener can be replaced by another one
+    /// for the same events without being attached again. It is also used when the events are
+    /// delegated to the mount element, see
+    /// [`Render::with_event_delegation`](super::Render::with_event_delegation). The listener
+    /// is attached with `attach` if it has none.
+    fn handler(&self) -> Option<DelegatedHandler> {
+        None
+    }
+    /// Returns the options the listener is attached with.
+    fn options(&self) -> ListenerOptions {
+        ListenerOptions::default()
+    }
+    /// Returns the listener as `Any`, to compare it with listeners of the same type.
+    fn as_any(&self) -> Option<&dyn Any> {
+        None
+    }
+    /// Returns `true` if `other` handles the same events with the same callback and options,
+    /// in which case the handler of `other` is kept instead.
+    fn same_as(&self, _other: &dyn Listener) -> bool {
+        false
+    }
+}
+
+/// Options of an event listener.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ListenerOptions {
+    /// Listens in the capture phase instead of the bubble phase.
+    pub capture: bool,
+    /// Makes the listener passive, so that the browser doesn't wait for it before scrolling.
+    /// By default only the `touchstart` and `touchmove` listeners are passive.
+    pub passive: Option<bool>,
+    /// Calls the listener for the first event only, until it is attached again.
+    pub once: bool,
+    /// Prevents the default action of the events before calling the listener. It has no
+    /// effect in passive listeners.
+    pub prevent_default: bool,
+    /// Stops the propagation of the events to the listeners of the parent elements before
+    /// calling the listener.
+    pub stop_propagation: bool,
+}
+
+impl ListenerOptions {
+    /// Returns `true` if the listener can be delegated to the mount element, which listens
+    /// in the bubble phase with the default passiveness. `once` listeners are attached to
+    /// their element, which remembers that they fired across renders.
+    pub(crate) fn is_delegable(&self) -> bool {
+        !self.capture && self.passive.is_none() && !self.once
+    }
+
+    /// Returns the options of the listener for `event_type` events attached to an element.
+    pub(crate) fn to_event_listener_options(self, event_type: &str) -> EventListenerOptions {
+        // We should only set passive event listeners for `touchstart` and `touchmove` by
+        // default. See here: https://developer.mozilla.org/en-US/docs/Web/API/EventTarget/addEventListener#Improving_scrolling_performance_with_passive_listeners
+        let passive = self
+            .passive
+            .unwrap_or(event_type == "touchstart" || event_type == "touchmove");
+        let phase = if self.capture {
+            EventListenerPhase::Capture
+        } else {
+            EventListenerPhase::Bubble
+        };
+        EventListenerOptions { phase, passive }
+    }
 }
 
 impl fmt::Debug for dyn Listener {
@@ -27,6 +94,9 @@ pub type Listeners = Vec<Rc<dyn Listener>>;
 /// A map of attributes.
 pub type Attributes = HashMap<String, String>;
 
+/// A map of DOM properties.
+pub type Properties = HashMap<String, PropertyValue>;
+
 /// A set of classes.
 #[derive(Debug, Clone, Default)]
 pub struct Classes {
@@ -136,6 +206,192 @@ impl PartialEq for Classes {
     }
 }
 
+/// A declaration of a [`Styles`] map.
+#[derive(Debug, Clone, PartialEq)]
+struct StyleValue {
+    value: String,
+    important: bool,
+}
+
+/// A map of inline style properties, which are set one by one on the
+/// [CSSStyleDeclaration](https://developer.mozilla.org/en-US/docs/Web/API/CSSStyleDeclaration)
+/// of an element. Properties are kept in insertion order, including custom properties like
+/// `--accent-color`.
+#[derive(Debug, Clone, Default)]
+pub struct Styles {
+    map: IndexMap<String, StyleValue>,
+}
+
+impl Styles {
+    /// Creates an empty map of styles.
+    pub fn new() -> Self {
+        Self {
+            map: IndexMap::new(),
+        }
+    }
+
+    /// Sets a property.
+    ///
+    /// If the property is already set, its value is replaced.
+    pub fn set(&mut self, property: &str, value: &str) {
+        self.insert(property, value, false);
+    }
+
+    /// Sets a property with the `!important` priority.
+    ///
+    /// If the property is already set, its value is replaced.
+    pub fn set_important(&mut self, property: &str, value: &str) {
+        self.insert(property, value, true);
+    }
+
+    fn insert(&mut self, property: &str, value: &str, important: bool) {
+        let value = StyleValue {
+            value: value.trim().to_owned(),
+            important,
+        };
+        self.map.insert(property.trim().to_owned(), value);
+    }
+
+    /// Removes a property.
+    pub fn remove(&mut self, property: &str) {
+        self.map.shift_remove(property);
+    }
+
+    /// Returns the value of a property.
+    pub fn get(&self, property: &str) -> Option<&str> {
+        self.map.get(property).map(|style| style.value.as_str())
+    }
+
+    /// Check a property has the `!important` priority.
+    pub fn is_important(&self, property: &str) -> bool {
+        self.map.get(property).is_some_and(|style| style.important)
+    }
+
+    /// Check the map is empty.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns the properties with their value and whether they are `!important`.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str, bool)> {
+        self.map
+            .iter()
+            .map(|(property, style)| (property.as_str(), style.value.as_str(), style.important))
+    }
+
+    /// Adds other styles to this map of styles, the other values win; returning itself.
+    pub fn extend<T: Into<Styles>>(mut self, other: T) -> Self {
+        self.map.extend(other.into().map);
+        self
+    }
+}
+
+impl fmt::Display for Styles {
+    /// Serializes the styles as the value of a `style` attribute.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (property, value, important)) in self.iter().enumerate() {
+            if index > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{}: {}", property, value)?;
+            if important {
+                f.write_str(" !important")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<&str> for Styles {
+    /// Parses the declarations of a `style` attribute. Semicolons in quotes or parentheses,
+    /// like in `url("a;b")`, don't end a declaration.
+    fn from(t: &str) -> Self {
+        let mut styles = Styles::new();
+        for declaration in split_declarations(t) {
+            let (property, value) = match declaration.find(':') {
+                Some(index) => (&declaration[..index], &declaration[index + 1..]),
+                None => continue,
+            };
+            let value = value.trim();
+            let priority = value.len().checked_sub("!important".len());
+            let (value, important) = match priority.and_then(|index| value.get(index..)) {
+                Some(priority) if priority.eq_ignore_ascii_case("!important") => {
+                    (&value[..value.len() - priority.len()], true)
+                }
+                _ => (value, false),
+            };
+            if !property.trim().is_empty() {
+                styles.insert(property, value, important);
+            }
+        }
+        styles
+    }
+}
+
+fn split_declarations(t: &str) -> Vec<&str> {
+    let mut declarations = Vec::new();
+    let mut start = 0;
+    let mut depth = 0usize;
+    let mut quote = None;
+    for (index, c) in t.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth = depth.saturating_sub(1),
+            (None, ';') if depth == 0 => {
+                declarations.push(&t[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    declarations.push(&t[start..]);
+    declarations
+}
+
+impl From<String> for Styles {
+    fn from(t: String) -> Self {
+        Styles::from(t.as_str())
+    }
+}
+
+impl From<&String> for Styles {
+    fn from(t: &String) -> Self {
+        Styles::from(t.as_str())
+    }
+}
+
+impl<T: AsRef<str>> From<Option<T>> for Styles {
+    fn from(t: Option<T>) -> Self {
+        t.as_ref()
+            .map(|s| <Styles as From<&str>>::from(s.as_ref()))
+            .unwrap_or_default()
+    }
+}
+
+impl<K: AsRef<str>, V: AsRef<str>> From<Vec<(K, V)>> for Styles {
+    fn from(t: Vec<(K, V)>) -> Self {
+        let mut styles = Styles::new();
+        for (property, value) in t {
+            styles.set(property.as_ref(), value.as_ref());
+        }
+        styles
+    }
+}
+
+/// Styles are equal when they set the same properties, whatever their order.
+impl PartialEq for Styles {
+    fn eq(&self, other: &Self) -> bool {
+        self.map.len() == other.map.len()
+            && self
+                .map
+                .iter()
+                .all(|(property, style)| other.map.get(property) == Some(style))
+    }
+}
+
 /// Patch for DOM node modification.
 #[derive(Debug, PartialEq)]
 pub enum Patch<ID, T> {
@@ -183,15 +439,88 @@ pub trait VDiff {
         next_sibling: NodeRef,
         ancestor: Option<VNode>,
     ) -> NodeRef;
+
+    /// Adopts DOM nodes which were already rendered from the same tree, for example by
+    /// the server, instead of creating new ones.
+    ///
+    /// Parameters:
+    /// - `parent_scope`: the parent `Scope` used for passing messages to the
+    ///   parent `Component`.
+    /// - `parent`: the parent node in the DOM.
+    /// - `next_sibling`: the next sibling, used to efficiently find where to
+    ///   put the node in later renders.
+    /// - `cursor`: the next DOM node of `parent` which is expected to match this node. It is
+    ///   advanced past the adopted nodes.
+    ///
+    /// Nodes which don't match are created and inserted before the `cursor` and the mismatch
+    /// is reported through `log`.
+    fn hydrate(
+        &mut self,
+        parent_scope: &AnyScope,
+        parent: &Element,
+        next_sibling: NodeRef,
+        cursor: &mut Option<Node>,
+    ) -> NodeRef;
+}
+
+/// This trait provides features to render a tree into HTML markup without a browser `Document`.
+pub trait VRender {
+    /// Writes the markup of the node to `html`.
+    ///
+    /// Parameters:
+    /// - `parent_scope`: the parent `Scope` of the components found in the tree. Components are
+    ///   created and rendered recursively through their `view` method.
+    /// - `svg`: whether the node is rendered inside of an `<svg>` element.
+    /// - `html`: the buffer the markup is written to.
+    fn render_html(&self, parent_scope: Option<&AnyScope>, svg: bool, html: &mut String);
+}
+
+/// Escapes text content for HTML markup.
+pub(crate) fn escape_text(text: &str, html: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            _ => html.push(c),
+        }
+    }
+}
+
+/// Escapes a double quoted attribute value for HTML markup.
+pub(crate) fn escape_attribute(value: &str, html: &mut String) {
+    for c in value.chars() {
+        match c {
+            '&' => html.push_str("&amp;"),
+            '"' => html.push_str("&quot;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            _ => html.push(c),
+        }
+    }
 }
 
 pub fn insert_node(node: &Node, parent: &Element, next_sibling: Option<Node>) {
-    match next_sibling {
-        Some(next_sibling) => parent
-            .insert_before(&node, Some(&next_sibling))
-            .expect("failed to insert tag before next sibling"),
-        None => parent.append_child(node).expect("failed to append child"),
-    };
+    Backend::insert_before(parent, node, next_sibling.as_ref());
+}
+
+/// Returns a reference to the DOM node pointed by a hydration cursor.
+pub(crate) fn cursor_ref(cursor: &Option<Node>) -> NodeRef {
+    let node_ref = NodeRef::default();
+    node_ref.set(cursor.clone());
+    node_ref
+}
+
+/// Removes the DOM nodes of `parent` starting at `cursor` which were not adopted
+/// during hydration.
+pub(crate) fn remove_unclaimed(parent: &Element, mut cursor: Option<Node>) {
+    while let Some(node) = cursor {
+        cursor = Backend::next_sibling(&node);
+        warn!("Hydration mismatch: removing unexpected node {:?}", Backend::node_name(&node));
+        if !Backend::remove_child(parent, &node) {
+            warn!("Node not found to remove during hydration");
+        }
+    }
 }
 
 /// Transform properties to the expected type.
@@ -199,3 +528,67 @@ pub trait Transformer<FROM, TO> {
     /// Transforms one type to another.
     fn transform(from: FROM) -> TO;
 }
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    fn declarations(styles: &Styles) -> Vec<(&str, &str, bool)> {
+        styles.iter().collect()
+    }
+
+    #[test]
+    fn parse_declarations() {
+        let styles = Styles::from(" color: red ;margin:0 auto;;");
+        assert_eq!(
+            declarations(&styles),
+            vec![("color", "red", false), ("margin", "0 auto", false)]
+        );
+    }
+
+    #[test]
+    fn parse_quotes_and_parentheses() {
+        let styles = Styles::from(
+            "background: url(\"a;b.png\"); content: 'c;d'; width: calc((100% - 2px) / 2)",
+        );
+        assert_eq!(
+            declarations(&styles),
+            vec![
+                ("background", "url(\"a;b.png\")", false),
+                ("content", "'c;d'", false),
+                ("width", "calc((100% - 2px) / 2)", false),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_priorities_and_custom_properties() {
+        let styles = Styles::from("color: red !important; --accent-color: #fff!IMPORTANT; top: 0");
+        assert_eq!(
+            declarations(&styles),
+            vec![
+                ("color", "red", true),
+                ("--accent-color", "#fff", true),
+                ("top", "0", false),
+            ]
+        );
+        assert_eq!(
+            styles.to_string(),
+            "color: red !important; --accent-color: #fff !important; top: 0"
+        );
+    }
+
+    #[test]
+    fn parse_skips_invalid_declarations() {
+        let styles = Styles::from("nonsense; : 1; left: 2");
+        assert_eq!(declarations(&styles), vec![("left", "2", false)]);
+    }
+
+    #[test]
+    fn styles_are_equal_in_any_order() {
+        assert_eq!(Styles::from("a: 1; b: 2"), Styles::from("b: 2; a: 1"));
+        assert_ne!(Styles::from("a: 1; b: 2"), Styles::from("b: 2; a: 3"));
+        assert_ne!(Styles::from("a: 1"), Styles::from("a: 1 !important"));
+        assert_ne!(Styles::from("a: 1"), Styles::from("a: 1; b: 2"));
+    }
+}
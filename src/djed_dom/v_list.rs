@@ -1,8 +1,8 @@
-use super::{VDiff, VNode, VText};
+use super::{VDiff, VNode, VRender, VText};
 use crate::djed::{AnyScope, NodeRef};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::ops::{Deref, DerefMut};
-use web_sys::Element;
+use super::backend::{Element, Node};
 
 /// This struct represents a fragment of the Virtual DOM tree.
 #[derive(Clone, Debug, PartialEq, Default)]
@@ -96,49 +96,401 @@ impl VDiff for VList {
                     }
                 }
             }
+        }
+
+        // Pair every left with the right it is going to be diffed against.
+        let mut rights: Vec<Option<VNode>> = ancestor_children.into_iter().map(Some).collect();
+        let matches = match_ancestors(&self.children, &rights);
 
-            // This warning should be removed in https://github.com/yewstack/yew/pull/1231
-            if !hash_set.is_empty() {
-                log::warn!("Keys currently have no effect");
+        // Detach all rights which are not reused by any left
+        let mut reused = vec![false; rights.len()];
+        for index in matches.iter().flatten() {
+            reused[*index] = true;
+        }
+        for (right, reused) in rights.iter_mut().zip(reused) {
+            if !reused {
+                if let Some(mut right) = right.take() {
+                    right.detach(parent);
+                }
             }
         }
 
-        // Process children
-        let lefts = self.children.iter_mut();
-        let mut rights = ancestor_children.into_iter().peekable();
-        let mut last_next_sibling = NodeRef::default();
-        let mut nodes: Vec<NodeRef> = lefts
-            .map(|left| {
-                let ancestor = rights.next();
-
-                // Create a new `next_sibling` reference which points to the next `right` or
-                // the outer list's `next_sibling` if there are no more `rights`.
-                let new_next_sibling = NodeRef::default();
-                if let Some(next_right) = rights.peek() {
-                    new_next_sibling.set(Some(next_right.first_node()));
-                } else {
-                    new_next_sibling.link(next_sibling.clone());
+        // Lefts whose rights are part of the longest increasing subsequence keep their
+        // DOM position, every other reused right has to be moved.
+        let stable = longest_increasing_subsequence(&matches);
+
+        // Process children from the last to the first one, so that the `next_sibling` of
+        // every left is the node reference of the already updated left that follows it.
+        let mut next_sibling = next_sibling;
+        for (index, left) in self.children.iter_mut().enumerate().rev() {
+            let ancestor = matches[index].and_then(|right| rights[right].take());
+            if let Some(ancestor) = &ancestor {
+                if !stable[index] {
+                    ancestor.move_before(parent, next_sibling.get());
                 }
+            }
+            next_sibling = left.apply(parent_scope, parent, next_sibling, ancestor);
+        }
+
+        next_sibling
+    }
+
+    fn hydrate(
+        &mut self,
+        parent_scope: &AnyScope,
+        parent: &Element,
+        next_sibling: NodeRef,
+        cursor: &mut Option<Node>,
+    ) -> NodeRef {
+        if self.children.is_empty() {
+            // The placeholder is not part of the markup
+            let placeholder = VText::new("".into());
+            self.children.push(placeholder.into());
+        }
 
-                // Update the next list item and then link the previous left's `next_sibling` to the returned `node` reference
-                // so that the previous left has an up-to-date `next_sibling` (important for mounting a `Component`)
-                let node = left.apply(parent_scope, parent, new_next_sibling.clone(), ancestor);
-                last_next_sibling.link(node.clone());
-                last_next_sibling = new_next_sibling;
-                node
-            })
-            .collect();
-
-        // If there are more `rights` than `lefts`, we need to make sure to link the last left's `next_sibling`
-        // to the outer list's `next_sibling` so that it doesn't point at a `right` that is detached.
+        // Link the `next_sibling` of every child to the node reference of the child
+        // which follows it, once that child is hydrated.
+        let mut first_node = None;
+        let mut last_next_sibling = NodeRef::default();
+        for child in self.children.iter_mut() {
+            let new_next_sibling = NodeRef::default();
+            let node = child.hydrate(parent_scope, parent, new_next_sibling.clone(), cursor);
+            last_next_sibling.link(node.clone());
+            last_next_sibling = new_next_sibling;
+            first_node.get_or_insert(node);
+        }
         last_next_sibling.link(next_sibling);
 
-        // Detach all extra rights
-        for mut right in rights {
-            right.detach(parent);
+        first_node.expect("VList should have at least one child")
+    }
+}
+
+impl VRender for VList {
+    fn render_html(&self, parent_scope: Option<&AnyScope>, svg: bool, html: &mut String) {
+        for child in self.children.iter() {
+            child.render_html(parent_scope, svg, html);
+        }
+    }
+}
+
+/// Finds the index of the right each left should be diffed against.
+///
+/// Keyed lefts are paired with the right carrying the same key, unkeyed lefts are
+/// paired positionally with the unkeyed rights.
+fn match_ancestors(lefts: &[VNode], rights: &[Option<VNode>]) -> Vec<Option<usize>> {
+    let mut keyed: HashMap<&str, usize> = HashMap::new();
+    let mut unkeyed = Vec::new();
+    for (index, right) in rights.iter().enumerate() {
+        match right.as_ref().and_then(|right| right.key().as_deref()) {
+            Some(key) => {
+                keyed.entry(key).or_insert(index);
+            }
+            None => unkeyed.push(index),
+        }
+    }
+
+    let mut unkeyed = unkeyed.into_iter();
+    lefts
+        .iter()
+        .map(|left| match left.key() {
+            Some(key) => keyed.remove(key.as_str()),
+            None => unkeyed.next(),
+        })
+        .collect()
+}
+
+/// Marks the lefts whose rights form the longest increasing subsequence of `matches`.
+fn longest_increasing_subsequence(matches: &[Option<usize>]) -> Vec<bool> {
+    // `tails[n]` holds the position in `matches` of the smallest tail of all
+    // increasing subsequences of length `n + 1` found so far.
+    let mut tails: Vec<usize> = Vec::new();
+    let mut predecessors: Vec<Option<usize>> = vec![None; matches.len()];
+    for (position, right) in matches.iter().enumerate() {
+        let right = match right {
+            Some(right) => *right,
+            None => continue,
+        };
+        let length = tails
+            .binary_search_by(|tail| matches[*tail].cmp(&Some(right)))
+            .unwrap_or_else(|length| length);
+        if length > 0 {
+            predecessors[position] = Some(tails[length - 1]);
+        }
+        if length == tails.len() {
+            tails.push(position);
+        } else {
+            tails[length] = position;
+        }
+    }
+
+    let mut stable = vec![false; matches.len()];
+    let mut position = tails.last().copied();
+    while let Some(current) = position {
+        stable[current] = true;
+        position = predecessors[current];
+    }
+    stable
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::djed::{Component, ComponentLink, Html, Props};
+    use crate::djed_dom::backend::MemoryNode;
+    use crate::djed_dom::{VComp, VTag};
+    use crate::testing::TestRenderer;
+    use std::cell::Cell;
+
+    /// Renders an item for every `(key, text)` pair.
+    struct Items(Vec<(Option<&'static str>, &'static str)>);
+
+    impl Component for Items {
+        type State = Vec<(Option<&'static str>, &'static str)>;
+        type Props = ();
+
+        fn create(_: (), _: ComponentLink<Self>) -> Self {
+            Items(Vec::new())
+        }
+
+        fn update(&mut self, items: Self::State) -> bool {
+            self.0 = items;
+            true
+        }
+
+        fn change(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn view(&self) -> Html {
+            let mut list = VTag::new("ul");
+            for (key, text) in &self.0 {
+                let mut item = VTag::new("li");
+                item.key = key.map(str::to_owned);
+                item.add_child(VText::new(text.to_string()).into());
+                list.add_child(item.into());
+            }
+            list.into()
+        }
+    }
+
+    fn keyed(keys: &[&'static str]) -> Vec<(Option<&'static str>, &'static str)> {
+        keys.iter().map(|key| (Some(*key), *key)).collect()
+    }
+
+    fn render(renderer: &TestRenderer<Items>, items: Vec<(Option<&'static str>, &'static str)>) {
+        renderer.link().send_message(items);
+        renderer.flush();
+    }
+
+    fn items(renderer: &TestRenderer<Items>) -> Vec<MemoryNode> {
+        renderer.find_by_tag("li")
+    }
+
+    /// Asserts that the items show `texts` and are the nodes of `before` at `positions`.
+    fn assert_items(
+        renderer: &TestRenderer<Items>,
+        texts: &[&str],
+        before: &[MemoryNode],
+        positions: &[usize],
+    ) {
+        let after = items(renderer);
+        let rendered: Vec<String> = after.iter().map(MemoryNode::text_content).collect();
+        assert_eq!(rendered, texts);
+        for (node, position) in after.iter().zip(positions) {
+            assert_eq!(node, &before[*position]);
+        }
+    }
+
+    #[test]
+    fn insert_keyed_at_the_top() {
+        let renderer = TestRenderer::<Items>::mount();
+        render(&renderer, keyed(&["a", "b", "c"]));
+        let before = items(&renderer);
+
+        render(&renderer, keyed(&["z", "a", "b", "c"]));
+        assert_items(&renderer, &["z", "a", "b", "c"], &before, &[]);
+        assert_eq!(&items(&renderer)[1..], &before[..]);
+    }
+
+    #[test]
+    fn reverse_keyed() {
+        let renderer = TestRenderer::<Items>::mount();
+        render(&renderer, keyed(&["a", "b", "c", "d"]));
+        let before = items(&renderer);
+
+        render(&renderer, keyed(&["d", "c", "b", "a"]));
+        assert_items(&renderer, &["d", "c", "b", "a"], &before, &[3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn move_keyed_to_the_middle() {
+        let renderer = TestRenderer::<Items>::mount();
+        render(&renderer, keyed(&["a", "b", "c", "d", "e"]));
+        let before = items(&renderer);
+
+        render(&renderer, keyed(&["b", "c", "a", "d", "e"]));
+        assert_items(
+            &renderer,
+            &["b", "c", "a", "d", "e"],
+            &before,
+            &[1, 2, 0, 3, 4],
+        );
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        let renderer = TestRenderer::<Items>::mount();
+        render(&renderer, keyed(&["a", "a", "b"]));
+        assert_eq!(renderer.html(), "<ul><li>a</li><li>a</li><li>b</li></ul>");
+
+        render(&renderer, keyed(&["b", "a", "a"]));
+        assert_eq!(renderer.html(), "<ul><li>b</li><li>a</li><li>a</li></ul>");
+    }
+
+    #[test]
+    fn mixed_keyed_and_unkeyed() {
+        let renderer = TestRenderer::<Items>::mount();
+        render(
+            &renderer,
+            vec![(Some("a"), "a"), (None, "1"), (Some("b"), "b"), (None, "2")],
+        );
+        let before = items(&renderer);
+
+        render(
+            &renderer,
+            vec![(None, "3"), (Some("b"), "b"), (None, "4"), (Some("a"), "a")],
+        );
+        // Unkeyed items are reused in order, keyed items follow their keys.
+        assert_items(&renderer, &["3", "b", "4", "a"], &before, &[1, 2, 3, 0]);
+    }
+
+    thread_local! {
+        static CREATED: Cell<usize> = Cell::new(0);
+    }
+
+    #[derive(Clone, PartialEq)]
+    struct Label(&'static str);
+
+    impl Props for Label {
+        type Builder = ();
+
+        fn builder() {}
+    }
+
+    /// Shows its label and the number of components created before it.
+    struct Counter {
+        label: &'static str,
+        id: usize,
+    }
+
+    impl Component for Counter {
+        type State = ();
+        type Props = Label;
+
+        fn create(label: Label, _: ComponentLink<Self>) -> Self {
+            let id = CREATED.with(|created| created.replace(created.get() + 1));
+            Counter { label: label.0, id }
         }
 
-        assert!(!nodes.is_empty(), "VList should have at least one child");
-        nodes.swap_remove(0)
+        fn update(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn change(&mut self, label: Label) -> bool {
+            self.label = label.0;
+            true
+        }
+
+        fn view(&self) -> Html {
+            let mut item = VTag::new("li");
+            item.add_child(VText::new(format!("{}{}", self.label, self.id)).into());
+            item.into()
+        }
+    }
+
+    struct Counters(Vec<&'static str>);
+
+    impl Component for Counters {
+        type State = Vec<&'static str>;
+        type Props = ();
+
+        fn create(_: (), _: ComponentLink<Self>) -> Self {
+            Counters(Vec::new())
+        }
+
+        fn update(&mut self, labels: Vec<&'static str>) -> bool {
+            self.0 = labels;
+            true
+        }
+
+        fn change(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn view(&self) -> Html {
+            let mut list = VTag::new("ul");
+            for label in &self.0 {
+                let key = Some(label.to_string());
+                list.add_child(VComp::new::<Counter>(Label(label), NodeRef::default(), key).into());
+            }
+            list.into()
+        }
+    }
+
+    #[test]
+    fn keyed_components_keep_their_state() {
+        CREATED.with(|created| created.set(0));
+        let renderer = TestRenderer::<Counters>::mount();
+        renderer.link().send_message(vec!["a", "b", "c"]);
+        renderer.flush();
+        // Children are applied from the last to the first one.
+        assert_eq!(renderer.html(), "<ul><li>a2</li><li>b1</li><li>c0</li></ul>");
+
+        renderer.link().send_message(vec!["c", "a", "d", "b"]);
+        renderer.flush();
+        assert_eq!(
+            renderer.html(),
+            "<ul><li>c0</li><li>a2</li><li>d3</li><li>b1</li></ul>"
+        );
+        assert_eq!(CREATED.with(Cell::get), 4);
+    }
+
+    #[test]
+    fn lis_of_nothing() {
+        assert_eq!(longest_increasing_subsequence(&[]), Vec::<bool>::new());
+        assert_eq!(
+            longest_increasing_subsequence(&[None, None, None]),
+            vec![false; 3]
+        );
+    }
+
+    #[test]
+    fn lis_of_decreasing() {
+        let stable = longest_increasing_subsequence(&[Some(3), Some(2), Some(1), Some(0)]);
+        assert_eq!(stable.iter().filter(|stable| **stable).count(), 1);
+    }
+
+    #[test]
+    fn lis_skips_unmatched() {
+        let matches = [Some(0), None, Some(3), Some(1), None, Some(2)];
+        assert_eq!(
+            longest_increasing_subsequence(&matches),
+            vec![true, false, false, true, false, true]
+        );
+    }
+
+    #[test]
+    fn match_keyed_and_unkeyed() {
+        let node = |key: Option<&str>| {
+            let mut tag = VTag::new("li");
+            tag.key = key.map(str::to_owned);
+            VNode::from(tag)
+        };
+        let rights = vec![Some(node(Some("a"))), Some(node(None)), Some(node(Some("b")))];
+        let lefts = vec![node(Some("b")), node(None), node(None), node(Some("c"))];
+        assert_eq!(
+            match_ancestors(&lefts, &rights),
+            vec![Some(2), Some(1), None, None]
+        );
     }
 }
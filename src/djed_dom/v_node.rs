@@ -1,10 +1,10 @@
-use super::{VChild, VComp, VDiff, VList, VTag, VText};
+use super::{VChild, VComp, VDiff, VList, VPortal, VRaw, VRender, VTag, VText};
 use crate::djed::{AnyScope, Component, NodeRef, Renderable};
 use log::warn;
 use std::cmp::PartialEq;
 use std::fmt;
 use std::iter::FromIterator;
-use web_sys::{Element, Node};
+use super::backend::{into_node, Backend, DomBackend, Element, Node};
 
 
 /// Bind virtual element to a DOM reference.
@@ -20,32 +20,74 @@ pub enum VNode {
     VList(VList),
     /// A holder for any `Node` (necessary for replacing node).
     VRef(Node),
+    /// A bind between `VPortal` and the host `Element` its content is rendered into.
+    VPortal(VPortal),
+    /// A bind between `VRaw` and the `Element` wrapping its markup.
+    VRaw(VRaw),
 }
 
 impl VNode {
     /// Returns the first DOM node that is used to designate the position of the virtual DOM node.
-    pub(crate) fn first_node(&self) -> Node {
+    /// It is `None` for a portal, which takes no room in its parent, and for a component or a
+    /// list which only renders portals.
+    pub(crate) fn first_node(&self) -> Option<Node> {
         match self {
-            VNode::VTag(vtag) => vtag
-                .reference
-                .as_ref()
-                .expect("VTag is not mounted")
-                .clone()
-                .into(),
+            VNode::VTag(vtag) => {
+                let element = vtag.reference.as_ref().expect("VTag is not mounted");
+                Some(into_node(element.clone()))
+            }
             VNode::VText(vtext) => {
                 let text_node = vtext.reference.as_ref().expect("VText is not mounted");
-                text_node.clone().into()
+                Some(into_node(text_node.clone()))
+            }
+            VNode::VComp(vcomp) => match vcomp.root_vnode() {
+                Some(root) => root.first_node(),
+                None => vcomp.node_ref.get(),
+            },
+            VNode::VList(vlist) => vlist.children.iter().find_map(VNode::first_node),
+            VNode::VRef(node) => Some(node.clone()),
+            VNode::VRaw(vraw) => {
+                let element = vraw.reference.as_ref().expect("VRaw is not mounted");
+                Some(into_node(element.clone()))
             }
-            VNode::VComp(vcomp) => vcomp.node_ref.get().expect("VComp is not mounted"),
-            VNode::VList(vlist) => vlist
-                .children
-                .get(0)
-                .expect("VList is not mounted")
-                .first_node(),
-            VNode::VRef(node) => node.clone(),
+            VNode::VPortal(_) => None,
         }
     }
 
+    /// Moves the DOM nodes of the virtual DOM node in front of `next_sibling`.
+    pub(crate) fn move_before(&self, parent: &Element, next_sibling: Option<Node>) {
+        match self {
+            VNode::VList(vlist) => {
+                for child in vlist.children.iter() {
+                    child.move_before(parent, next_sibling.clone());
+                }
+            }
+            VNode::VComp(vcomp) => match vcomp.root_vnode() {
+                Some(root) => root.move_before(parent, next_sibling),
+                None => {
+                    if let Some(node) = self.first_node() {
+                        super::insert_node(&node, parent, next_sibling);
+                    }
+                }
+            },
+            // The content of a portal stays in its host
+            VNode::VPortal(_) => {}
+            _ => {
+                if let Some(node) = self.first_node() {
+                    super::insert_node(&node, parent, next_sibling);
+                }
+            }
+        }
+    }
+
+    /// Renders the virtual DOM node into an HTML string without a browser `Document`.
+    /// Components in the tree are created and rendered recursively.
+    pub fn render_to_string(&self) -> String {
+        let mut html = String::new();
+        self.render_html(None, false, &mut html);
+        html
+    }
+
     pub fn key(&self) -> &Option<String> {
         match self {
             VNode::VTag(vtag) => &vtag.key,
@@ -53,6 +95,8 @@ impl VNode {
             VNode::VComp(vcomp) => &vcomp.key,
             VNode::VList(vlist) => &vlist.key,
             VNode::VRef(_) => &None,
+            VNode::VPortal(_) => &None,
+            VNode::VRaw(vraw) => &vraw.key,
         }
     }
 }
@@ -66,10 +110,12 @@ impl VDiff for VNode {
             VNode::VComp(ref mut vcomp) => vcomp.detach(parent),
             VNode::VList(ref mut vlist) => vlist.detach(parent),
             VNode::VRef(ref node) => {
-                if parent.remove_child(node).is_err() {
+                if !Backend::remove_child(parent, node) {
                     warn!("Node not found to remove VRef");
                 }
             }
+            VNode::VPortal(ref mut vportal) => vportal.detach(parent),
+            VNode::VRaw(ref mut vraw) => vraw.detach(parent),
         }
     }
 
@@ -103,6 +149,54 @@ impl VDiff for VNode {
                 super::insert_node(node, parent, next_sibling.get());
                 NodeRef::new(node.clone())
             }
+            VNode::VPortal(ref mut vportal) => {
+                vportal.apply(parent_scope, parent, next_sibling, ancestor)
+            }
+            VNode::VRaw(ref mut vraw) => vraw.apply(parent_scope, parent, next_sibling, ancestor),
+        }
+    }
+
+    fn hydrate(
+        &mut self,
+        parent_scope: &AnyScope,
+        parent: &Element,
+        next_sibling: NodeRef,
+        cursor: &mut Option<Node>,
+    ) -> NodeRef {
+        match *self {
+            VNode::VTag(ref mut vtag) => vtag.hydrate(parent_scope, parent, next_sibling, cursor),
+            VNode::VText(ref mut vtext) => {
+                vtext.hydrate(parent_scope, parent, next_sibling, cursor)
+            }
+            VNode::VComp(ref mut vcomp) => {
+                vcomp.hydrate(parent_scope, parent, next_sibling, cursor)
+            }
+            VNode::VList(ref mut vlist) => {
+                vlist.hydrate(parent_scope, parent, next_sibling, cursor)
+            }
+            // A referenced node can't be part of the markup, so it is inserted as is
+            VNode::VRef(ref node) => {
+                super::insert_node(node, parent, cursor.clone());
+                NodeRef::new(node.clone())
+            }
+            VNode::VPortal(ref mut vportal) => {
+                vportal.hydrate(parent_scope, parent, next_sibling, cursor)
+            }
+            VNode::VRaw(ref mut vraw) => vraw.hydrate(parent_scope, parent, next_sibling, cursor),
+        }
+    }
+}
+
+impl VRender for VNode {
+    fn render_html(&self, parent_scope: Option<&AnyScope>, svg: bool, html: &mut String) {
+        match *self {
+            VNode::VTag(ref vtag) => vtag.render_html(parent_scope, svg, html),
+            VNode::VText(ref vtext) => vtext.render_html(parent_scope, svg, html),
+            VNode::VComp(ref vcomp) => vcomp.render_html(parent_scope, svg, html),
+            VNode::VList(ref vlist) => vlist.render_html(parent_scope, svg, html),
+            VNode::VRef(_) => warn!("VRef nodes can't be rendered to a string"),
+            VNode::VPortal(ref vportal) => vportal.render_html(parent_scope, svg, html),
+            VNode::VRaw(ref vraw) => vraw.render_html(parent_scope, svg, html),
         }
     }
 }
@@ -131,6 +225,18 @@ impl From<VTag> for VNode {
     }
 }
 
+impl From<VPortal> for VNode {
+    fn from(vportal: VPortal) -> Self {
+        VNode::VPortal(vportal)
+    }
+}
+
+impl From<VRaw> for VNode {
+    fn from(vraw: VRaw) -> Self {
+        VNode::VRaw(vraw)
+    }
+}
+
 impl From<VComp> for VNode {
     fn from(vcomp: VComp) -> Self {
         VNode::VComp(vcomp)
@@ -176,6 +282,8 @@ impl fmt::Debug for VNode {
             VNode::VComp(ref vcomp) => vcomp.fmt(f),
             VNode::VList(ref vlist) => vlist.fmt(f),
             VNode::VRef(ref vref) => vref.fmt(f),
+            VNode::VPortal(ref vportal) => vportal.fmt(f),
+            VNode::VRaw(ref vraw) => vraw.fmt(f),
         }
     }
 }
@@ -187,9 +295,167 @@ impl PartialEq for VNode {
             (VNode::VText(a), VNode::VText(b)) => a == b,
             (VNode::VList(a), VNode::VList(b)) => a == b,
             (VNode::VRef(a), VNode::VRef(b)) => a == b,
+            (VNode::VPortal(a), VNode::VPortal(b)) => a == b,
+            (VNode::VRaw(a), VNode::VRaw(b)) => a == b,
             // Need to improve PartialEq for VComp before enabling
             (VNode::VComp(_), VNode::VComp(_)) => false,
             _ => false,
         }
     }
 }
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::djed::{ComponentLink, Html};
+    use crate::djed_dom::backend::MemoryNode;
+    use crate::testing::TestRenderer;
+
+    thread_local! {
+        static HOST: MemoryNode = MemoryNode::new_element("aside");
+    }
+
+    struct Modal;
+
+    impl Component for Modal {
+        type State = ();
+        type Props = ();
+
+        fn create(_: (), _: ComponentLink<Self>) -> Self {
+            Modal
+        }
+
+        fn update(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn change(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn view(&self) -> Html {
+            let content = VTag::new("dialog").into();
+            VPortal::new(content, HOST.with(Clone::clone)).into()
+        }
+    }
+
+    /// Renders a modal at the end of its parent, or a tag in place of it.
+    struct Page {
+        modal: bool,
+        list: bool,
+    }
+
+    impl Component for Page {
+        type State = (bool, bool);
+        type Props = ();
+
+        fn create(_: (), _: ComponentLink<Self>) -> Self {
+            Page {
+                modal: true,
+                list: false,
+            }
+        }
+
+        fn update(&mut self, (modal, list): (bool, bool)) -> bool {
+            self.modal = modal;
+            self.list = list;
+            true
+        }
+
+        fn change(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn view(&self) -> Html {
+            let mut page = VTag::new("main");
+            page.add_child(VTag::new("h1").into());
+            let last = if !self.modal {
+                VTag::new("p").into()
+            } else if self.list {
+                let mut list = VList::new();
+                list.add_child(VComp::new::<Modal>((), NodeRef::default(), None).into());
+                list.into()
+            } else {
+                VComp::new::<Modal>((), NodeRef::default(), None).into()
+            };
+            page.add_child(last);
+            page.into()
+        }
+    }
+
+    fn replace_modal(list: bool) {
+        let renderer = TestRenderer::<Page>::mount();
+        renderer.link().send_message((true, list));
+        renderer.flush();
+        assert_eq!(renderer.html(), "<main><h1></h1></main>");
+        assert_eq!(HOST.with(MemoryNode::inner_html), "<dialog></dialog>");
+
+        renderer.link().send_message((false, list));
+        renderer.flush();
+        assert_eq!(renderer.html(), "<main><h1></h1><p></p></main>");
+        assert_eq!(HOST.with(MemoryNode::inner_html), "");
+
+        renderer.link().send_message((true, list));
+        renderer.flush();
+        assert_eq!(renderer.html(), "<main><h1></h1></main>");
+        assert_eq!(HOST.with(MemoryNode::inner_html), "<dialog></dialog>");
+    }
+
+    #[test]
+    fn replace_component_rendering_a_portal() {
+        replace_modal(false);
+    }
+
+    #[test]
+    fn replace_list_starting_with_a_portal() {
+        replace_modal(true);
+    }
+
+    /// Renders a list with a modal between two tags, or only the two tags.
+    struct Toolbar(bool);
+
+    impl Component for Toolbar {
+        type State = bool;
+        type Props = ();
+
+        fn create(_: (), _: ComponentLink<Self>) -> Self {
+            Toolbar(true)
+        }
+
+        fn update(&mut self, modal: bool) -> bool {
+            self.0 = modal;
+            true
+        }
+
+        fn change(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn view(&self) -> Html {
+            let mut toolbar = VList::new();
+            toolbar.add_child(VTag::new("a").into());
+            if self.0 {
+                toolbar.add_child(VComp::new::<Modal>((), NodeRef::default(), None).into());
+            }
+            toolbar.add_child(VTag::new("i").into());
+            toolbar.into()
+        }
+    }
+
+    #[test]
+    fn replace_portal_by_its_next_sibling() {
+        let renderer = TestRenderer::<Toolbar>::mount();
+        assert_eq!(renderer.html(), "<a></a><i></i>");
+
+        // The tag after the modal is diffed against it, and the old tag is detached
+        renderer.link().send_message(false);
+        renderer.flush();
+        assert_eq!(renderer.html(), "<a></a><i></i>");
+        assert_eq!(HOST.with(MemoryNode::inner_html), "");
+
+        renderer.link().send_message(true);
+        renderer.flush();
+        assert_eq!(renderer.html(), "<a></a><i></i>");
+        assert_eq!(HOST.with(MemoryNode::inner_html), "<dialog></dialog>");
+    }
+}
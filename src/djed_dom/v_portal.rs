@@ -0,0 +1,106 @@
+use super::{VDiff, VNode, VRender};
+use crate::djed::{AnyScope, NodeRef};
+use log::warn;
+use super::backend::{Element, Node};
+
+/// A virtual node which renders its content into another DOM element, the host, instead of
+/// its parent. The content keeps the scope of the portal, so callbacks, context lookup and
+/// error boundaries work as if it was rendered in place.
+#[derive(Clone, Debug)]
+pub struct VPortal {
+    /// The element which the content is rendered into.
+    pub host: Element,
+    /// The node of the host which the content is inserted before. The content is appended
+    /// to the host if it is `None`.
+    pub host_next_sibling: NodeRef,
+    /// The content rendered into the host.
+    pub node: Box<VNode>,
+}
+
+impl VPortal {
+    /// Creates a portal which appends `content` to the `host`.
+    pub fn new(content: VNode, host: Element) -> Self {
+        VPortal {
+            host,
+            host_next_sibling: NodeRef::default(),
+            node: Box::new(content),
+        }
+    }
+
+    /// Creates a portal which inserts `content` in the `host` before `next_sibling`.
+    pub fn new_before(content: VNode, host: Element, next_sibling: Option<Node>) -> Self {
+        VPortal {
+            host,
+            host_next_sibling: next_sibling.map(NodeRef::new).unwrap_or_default(),
+            node: Box::new(content),
+        }
+    }
+}
+
+impl VDiff for VPortal {
+    /// Removes the content from the host.
+    fn detach(&mut self, _parent: &Element) {
+        self.node.detach(&self.host);
+    }
+
+    /// Renders the content into the host. The portal takes no room in its parent, so it
+    /// returns the next sibling as its position there.
+    fn apply(
+        &mut self,
+        parent_scope: &AnyScope,
+        parent: &Element,
+        next_sibling: NodeRef,
+        ancestor: Option<VNode>,
+    ) -> NodeRef {
+        let ancestor_node = match ancestor {
+            Some(VNode::VPortal(mut portal)) => {
+                if portal.host == self.host {
+                    Some(*portal.node)
+                } else {
+                    portal.detach(parent);
+                    None
+                }
+            }
+            Some(mut ancestor) => {
+                ancestor.detach(parent);
+                None
+            }
+            None => None,
+        };
+
+        if let Some(delegation) = &parent_scope.delegation {
+            delegation.add_root(&self.host);
+        }
+        self.node.apply(
+            parent_scope,
+            &self.host,
+            self.host_next_sibling.clone(),
+            ancestor_node,
+        );
+        next_sibling
+    }
+
+    /// The content of a portal is not part of the markup of its parent, so it is rendered
+    /// into the host from scratch.
+    fn hydrate(
+        &mut self,
+        parent_scope: &AnyScope,
+        parent: &Element,
+        next_sibling: NodeRef,
+        _cursor: &mut Option<Node>,
+    ) -> NodeRef {
+        self.apply(parent_scope, parent, next_sibling, None)
+    }
+}
+
+impl VRender for VPortal {
+    fn render_html(&self, _parent_scope: Option<&AnyScope>, _svg: bool, _html: &mut String) {
+        warn!("VPortal nodes can't be rendered to a string, their content is skipped");
+    }
+}
+
+impl PartialEq for VPortal {
+    fn eq(&self, other: &Self) -> bool {
+        self.host == other.host && self.node == other.node
+    }
+}
@@ -0,0 +1,147 @@
+use super::{VDiff, VNode, VRender};
+use crate::djed::{AnyScope, NodeRef};
+use log::warn;
+use std::borrow::Cow;
+use super::backend::{into_node, Backend, DomBackend, Element, Node};
+use super::v_tag::SVG_NAMESPACE;
+
+/// A virtual node for a string of HTML markup, which is set as the inner HTML of a wrapper
+/// element. The markup is only applied again when it changes.
+///
+/// The markup is inserted as is. Untrusted markup, like user generated content, has to be
+/// cleaned with [`VRaw::sanitize`] first.
+#[derive(Clone, Debug)]
+pub struct VRaw {
+    /// The tag of the wrapper element.
+    pub tag: Cow<'static, str>,
+    /// The markup set as the content of the wrapper.
+    pub html: String,
+    /// The key of the node, used to reorder lists.
+    pub key: Option<String>,
+    /// A reference to the wrapper `Element`.
+    pub reference: Option<Element>,
+}
+
+impl VRaw {
+    /// Creates a raw node which wraps the trusted `html` markup in a `<div>`.
+    pub fn new(html: impl Into<String>) -> Self {
+        VRaw::with_tag("div", html)
+    }
+
+    /// Creates a raw node which wraps the trusted `html` markup in an element with the `tag`.
+    pub fn with_tag(tag: impl Into<Cow<'static, str>>, html: impl Into<String>) -> Self {
+        VRaw {
+            tag: tag.into(),
+            html: html.into(),
+            key: None,
+            reference: None,
+        }
+    }
+
+    /// Replaces the markup with the one returned by the `sanitizer`, for example one which
+    /// strips scripts and event handler attributes.
+    pub fn sanitize(mut self, sanitizer: impl FnOnce(&str) -> String) -> Self {
+        self.html = sanitizer(&self.html);
+        self
+    }
+
+    fn create_element(&self, parent: &Element) -> Element {
+        if Backend::namespace_uri(parent).is_some_and(|ns| ns == SVG_NAMESPACE) {
+            Backend::create_element(&self.tag, Some(SVG_NAMESPACE))
+        } else {
+            Backend::create_element(&self.tag, None)
+        }
+    }
+}
+
+impl VDiff for VRaw {
+    /// Removes the wrapper from its parent.
+    fn detach(&mut self, parent: &Element) {
+        let element = self
+            .reference
+            .take()
+            .expect("tried to remove not rendered VRaw from DOM");
+        if !Backend::remove_child(parent, &into_node(element)) {
+            warn!("Node not found to remove VRaw");
+        }
+    }
+
+    /// Reuses the wrapper of an ancestor with the same tag, and only sets the markup when it
+    /// changed.
+    fn apply(
+        &mut self,
+        _parent_scope: &AnyScope,
+        parent: &Element,
+        next_sibling: NodeRef,
+        ancestor: Option<VNode>,
+    ) -> NodeRef {
+        if let Some(mut ancestor) = ancestor {
+            if let VNode::VRaw(vraw) = &mut ancestor {
+                if vraw.tag == self.tag {
+                    let element = vraw
+                        .reference
+                        .take()
+                        .expect("Rendered VRaw nodes should have a ref");
+                    if vraw.html != self.html {
+                        Backend::set_inner_html(&element, &self.html);
+                    }
+                    self.reference = Some(element.clone());
+                    return NodeRef::new(into_node(element));
+                }
+            }
+
+            ancestor.detach(parent);
+        }
+
+        let element = self.create_element(parent);
+        Backend::set_inner_html(&element, &self.html);
+        super::insert_node(&into_node(element.clone()), parent, next_sibling.get());
+        self.reference = Some(element.clone());
+        NodeRef::new(into_node(element))
+    }
+
+    /// Adopts the wrapper under the cursor when it has the same tag. Its content was rendered
+    /// from the same markup, so it is kept as is.
+    fn hydrate(
+        &mut self,
+        parent_scope: &AnyScope,
+        parent: &Element,
+        _next_sibling: NodeRef,
+        cursor: &mut Option<Node>,
+    ) -> NodeRef {
+        let element = cursor
+            .as_ref()
+            .and_then(Backend::as_element)
+            .filter(|element| Backend::tag_name(element).eq_ignore_ascii_case(&self.tag));
+        match element {
+            Some(element) => {
+                *cursor = Backend::next_sibling(&into_node(element.clone()));
+                self.reference = Some(element.clone());
+                NodeRef::new(into_node(element))
+            }
+            None => {
+                warn!("Hydration mismatch: expected <{}> with raw markup", self.tag);
+                self.apply(parent_scope, parent, super::v_dom::cursor_ref(cursor), None)
+            }
+        }
+    }
+}
+
+impl VRender for VRaw {
+    /// Writes the wrapper with the markup verbatim.
+    fn render_html(&self, _parent_scope: Option<&AnyScope>, _svg: bool, html: &mut String) {
+        html.push('<');
+        html.push_str(&self.tag);
+        html.push('>');
+        html.push_str(&self.html);
+        html.push_str("</");
+        html.push_str(&self.tag);
+        html.push('>');
+    }
+}
+
+impl PartialEq for VRaw {
+    fn eq(&self, other: &Self) -> bool {
+        self.tag == other.tag && self.html == other.html
+    }
+}
@@ -1,21 +1,38 @@
-use super::{Attributes, Listener, Listeners, Patch, Transformer, VDiff, VList, VNode};
+use super::{
+    Attributes, Listener, Listeners, Patch, Properties, Styles, Transformer, VDiff, VList, VNode,
+    VRender,
+};
+use super::delegation::{DelegatedListeners, EventDelegation, Handler};
 use crate::djed::{AnyScope, NodeRef};
-use crate::utils::document;
 use log::warn;
 use std::borrow::Cow;
 use std::cmp::PartialEq;
+use std::collections::BTreeMap;
+use std::cell::{Cell, RefCell};
+use std::fmt;
 use std::rc::Rc;
-use gloo::events::EventListener;
-use std::ops::Deref;
-use wasm_bindgen::JsCast;
-use web_sys::{
-    Element, HtmlInputElement as InputElement, HtmlTextAreaElement as TextAreaElement, HtmlButtonElement
+use super::backend::{
+    into_node, Backend, DomBackend, Element, ListenerHandle, Node, PropertyValue,
 };
 
 
 /// SVG namespace string used for creating svg elements
 pub const SVG_NAMESPACE: &str = "http://www.w3.org/2000/svg";
 
+/// Elements which never have children and are written without a closing tag
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// Attributes which are either present or absent, their value is ignored by browsers
+const BOOLEAN_ATTRIBUTES: &[&str] = &[
+    "allowfullscreen", "async", "autofocus", "autoplay", "checked", "controls", "default",
+    "defer", "disabled", "formnovalidate", "hidden", "inert", "ismap", "loop", "multiple",
+    "muted", "nomodule", "novalidate", "open", "playsinline", "readonly", "required",
+    "reversed", "selected",
+];
+
 /// Default namespace for html elements
 //pub const HTML_NAMESPACE: &str = "http://www.w3.org/1999/xhtml";
 
@@ -25,6 +42,8 @@ enum ElementType {
     Input,
     Textarea,
     Button,
+    Select,
+    Option,
     Other,
 }
 
@@ -34,6 +53,8 @@ impl ElementType {
             "input" => Self::Input,
             "textarea" => Self::Textarea,
             "button" => Self::Button,
+            "select" => Self::Select,
+            "option" => Self::Option,
             _ => Self::Other,
         }
     }
@@ -54,11 +75,19 @@ pub struct VTag {
     pub listeners: Listeners,
     /// List of attributes.
     pub attributes: Attributes,
+    /// Inline styles, which are set property by property instead of as a `style` attribute.
+    pub styles: Styles,
+    /// List of DOM properties, which are set on the element instead of being written as
+    /// attributes.
+    pub properties: Properties,
     /// List of children nodes
     pub children: VList,
     /// Contains a value of an
     /// [InputElement](https://developer.mozilla.org/en-US/docs/Web/HTML/Element/input).
     pub value: Option<String>,
+    /// Contains the values of the selected options of a
+    /// [multiple select](https://developer.mozilla.org/en-US/docs/Web/HTML/Element/select#attr-multiple).
+    pub selected_values: Option<Vec<String>>,
     /// Contains
     /// [kind](https://developer.mozilla.org/en-US/docs/Web/HTML/Element/input#Form_%3Cinput%3E_types)
     /// value of an `InputElement`.
@@ -72,7 +101,10 @@ pub struct VTag {
     /// A node reference used for DOM access in Component lifecycle methods
     pub node_ref: NodeRef,
     /// Keeps handler for attached listeners to have an opportunity to drop them later.
-    captured: Vec<EventListener>,
+    captured: Vec<AttachedListener>,
+    /// Keeps the registration of the listeners handled by the mount element, if the events
+    /// are delegated.
+    delegated: Option<DelegatedListeners>,
 
     pub key: Option<String>,
 }
@@ -85,13 +117,17 @@ impl Clone for VTag {
             reference: None,
             listeners: self.listeners.clone(),
             attributes: self.attributes.clone(),
+            styles: self.styles.clone(),
+            properties: self.properties.clone(),
             children: self.children.clone(),
             value: self.value.clone(),
+            selected_values: self.selected_values.clone(),
             kind: self.kind.clone(),
             checked: self.checked,
             node_ref: self.node_ref.clone(),
             key: self.key.clone(),
             captured: Vec::new(),
+            delegated: None,
         }
     }
 }
@@ -106,12 +142,16 @@ impl VTag {
             element_type,
             reference: None,
             attributes: Attributes::new(),
+            styles: Styles::new(),
+            properties: Properties::new(),
             listeners: Vec::new(),
             captured: Vec::new(),
+            delegated: None,
             children: VList::new(),
             node_ref: NodeRef::default(),
             key: None,
             value: None,
+            selected_values: None,
             kind: None,
             // In HTML node `checked` attribute sets `defaultChecked` parameter,
             // but we use own field to control real `checked` parameter
@@ -140,6 +180,14 @@ impl VTag {
         self.value = Some(value.to_string());
     }
 
+    /// Selects the options of a multiple
+    /// [SelectElement](https://developer.mozilla.org/en-US/docs/Web/HTML/Element/select)
+    /// whose value is one of `values`. The selection is restored after every render, like the
+    /// `value` of a single select set with [`VTag::set_value`].
+    pub fn set_selected_values<T: ToString>(&mut self, values: impl IntoIterator<Item = T>) {
+        self.selected_values = Some(values.into_iter().map(|value| value.to_string()).collect());
+    }
+
     /// Sets `kind` property of an
     /// [InputElement](https://developer.mozilla.org/en-US/docs/Web/HTML/Element/input).
     /// Same as set `type` attribute.
@@ -158,9 +206,13 @@ impl VTag {
     /// it set as attribute. We use workarounds for:
     /// `type/kind`, `value` and `checked`.
     ///
+    /// Boolean attributes like `disabled` set to `true` or `false` are made present or
+    /// absent, see [`VTag::set_boolean_attribute`]. The declarations of a `style` attribute
+    /// are added to the [`Styles`] of the node.
+    ///
     /// If this virtual node has this attribute present, the value is replaced.
     pub fn add_attribute<T: ToString>(&mut self, name: &str, value: &T) {
-        self.attributes.insert(name.to_owned(), value.to_string());
+        self.insert_attribute(name.to_owned(), value.to_string());
     }
 
     /// Adds attributes to a virtual node. Not every attribute works when
@@ -168,8 +220,62 @@ impl VTag {
     /// `type/kind`, `value` and `checked`.
     pub fn add_attributes(&mut self, attrs: Vec<(String, String)>) {
         for (name, value) in attrs {
-            self.attributes.insert(name, value);
+            self.insert_attribute(name, value);
+        }
+    }
+
+    fn insert_attribute(&mut self, name: String, value: String) {
+        if name == "style" {
+            let styles = std::mem::take(&mut self.styles);
+            self.styles = styles.extend(value);
+            return;
         }
+        if BOOLEAN_ATTRIBUTES.contains(&name.as_str()) {
+            match value.as_str() {
+                "true" => return self.set_boolean_attribute(&name, true),
+                "false" => return self.set_boolean_attribute(&name, false),
+                _ => {}
+            }
+        }
+        self.attributes.insert(name, value);
+    }
+
+    /// Makes a boolean attribute like `disabled`, `hidden` or `selected` present or absent.
+    /// A present boolean attribute has an empty value.
+    pub fn set_boolean_attribute(&mut self, name: &str, present: bool) {
+        if present {
+            self.attributes.insert(name.to_owned(), String::new());
+        } else {
+            self.attributes.remove(name);
+        }
+    }
+
+    /// Sets an inline style property, like `color` or a custom property like `--gap`.
+    ///
+    /// If this virtual node has this style property set, the value is replaced.
+    pub fn add_style(&mut self, property: &str, value: &str) {
+        self.styles.set(property, value);
+    }
+
+    /// Sets an inline style property with the `!important` priority.
+    pub fn add_important_style(&mut self, property: &str, value: &str) {
+        self.styles.set_important(property, value);
+    }
+
+    /// Adds inline styles to a virtual node, the added values win.
+    pub fn add_styles<T: Into<Styles>>(&mut self, styles: T) {
+        let current = std::mem::take(&mut self.styles);
+        self.styles = current.extend(styles);
+    }
+
+    /// Sets a DOM property of the element, like `selectedIndex` of a `<select>` or a
+    /// property of a custom element. Properties are set on the element when they change
+    /// and reset to `undefined` when they are removed. They aren't written to the markup
+    /// rendered on the server.
+    ///
+    /// If this virtual node has this property set, the value is replaced.
+    pub fn set_property(&mut self, name: &str, value: impl Into<PropertyValue>) {
+        self.properties.insert(name.to_owned(), value.into());
     }
 
     /// Adds new listener to the node.
@@ -188,39 +294,106 @@ impl VTag {
         }
     }
 
-    /// Every render it removes all listeners and attach it back later
-    /// TODO(#943): Compare references of handler to do listeners update better
-    fn recreate_listeners(&mut self, ancestor: &mut Option<Box<Self>>) {
+    /// Attaches the listeners to the element. The listeners of the ancestor which handle the
+    /// same events with the same options are kept, and only their handler is replaced when
+    /// their callback changed. The other listeners of the ancestor are detached.
+    /// When the events are delegated, the handlers are registered for the mount element
+    /// instead, reusing the registration of the ancestor.
+    fn recreate_listeners(
+        &mut self,
+        ancestor: &mut Option<Box<Self>>,
+        delegation: Option<&EventDelegation>,
+    ) {
+        let mut attached = Vec::new();
+        let mut previous = None;
         if let Some(ancestor) = ancestor.as_mut() {
-            ancestor.captured.clear();
+            attached = std::mem::take(&mut ancestor.captured);
+            previous = ancestor.delegated.take();
         }
 
         let element = self.reference.clone().expect("element expected");
-
+        let mut delegated = Vec::new();
         for listener in self.listeners.drain(..) {
-            let handle = listener.attach(&element);
-            self.captured.push(handle);
+            if delegation.is_some() && listener.options().is_delegable() {
+                delegated.push(listener);
+                continue;
+            }
+            let reused = attached
+                .iter_mut()
+                .position(|attached| attached.update(&listener));
+            let attached = match reused {
+                Some(index) => attached.remove(index),
+                None => AttachedListener::attach(&element, listener),
+            };
+            self.captured.push(attached);
+        }
+
+        let delegation = match delegation {
+            Some(delegation) => delegation,
+            None => return,
+        };
+        let unchanged = previous
+            .as_ref()
+            .is_some_and(|previous| previous.handles(&element, &delegated));
+        if unchanged {
+            self.delegated = previous;
+            return;
         }
+        let mut handlers = Vec::with_capacity(delegated.len());
+        let mut listeners = Vec::with_capacity(delegated.len());
+        for listener in delegated {
+            match listener.handler() {
+                Some(handler) => {
+                    delegation.listen(handler.0);
+                    handlers.push(handler);
+                    listeners.push(listener);
+                }
+                None => self
+                    .captured
+                    .push(AttachedListener::attach(&element, listener)),
+            }
+        }
+        self.delegated = DelegatedListeners::register(&element, listeners, handlers, previous);
     }
 
     fn refresh_value(&mut self) {
         if let Some(element) = self.reference.as_ref() {
-            if self.element_type == ElementType::Input {
-                let input_el = element.dyn_ref::<InputElement>();
-                if let Some(input) = input_el {
-                    let current_value = input.value();
+            if self.element_type == ElementType::Input || self.element_type == ElementType::Textarea
+            {
+                if let Some(current_value) = Backend::value(element) {
                     self.set_value(&current_value)
                 }
-            } else if self.element_type == ElementType::Textarea {
-                let textarea_el = element.dyn_ref::<TextAreaElement>();
-                if let Some(tae) = textarea_el {
-                    let current_value = &tae.value();
-                    self.set_value(&current_value)
+            } else if self.element_type == ElementType::Select {
+                // Only the controlled parts of a select are read back
+                if self.value.is_some() {
+                    self.value = Backend::value(element);
+                }
+                if self.selected_values.is_some() {
+                    self.selected_values = Some(Backend::selected_values(element));
                 }
             }
         }
     }
 
+    /// Restores the controlled selection of a `select`. It has to be done once the options
+    /// are rendered, and every time since the user may have changed it.
+    fn apply_selection(&self, ancestor: Option<&Self>) {
+        if self.element_type != ElementType::Select {
+            return;
+        }
+        let element = self.reference.as_ref().expect("element expected");
+        if let Some(value) = &self.value {
+            if ancestor.is_none_or(|ancestor| ancestor.value.as_ref() != Some(value)) {
+                Backend::set_value(element, value);
+            }
+        }
+        if let Some(values) = &self.selected_values {
+            if ancestor.is_none_or(|ancestor| ancestor.selected_values.as_ref() != Some(values)) {
+                Backend::set_selected_values(element, values);
+            }
+        }
+    }
+
     /// This handles patching of attributes when the keys are equal but
     /// the values are different.
     fn diff_attributes<'a>(
@@ -250,6 +423,58 @@ impl VTag {
         to_add_or_replace.chain(to_remove)
     }
 
+    /// Similar to `diff_attributes`, for the inline style properties.
+    fn diff_styles<'a>(
+        &'a self,
+        ancestor: &'a Option<Box<Self>>,
+    ) -> impl Iterator<Item = Patch<&'a str, (&'a str, bool)>> + 'a {
+        let ancestor_styles = ancestor.as_ref().map(|ancestor| &ancestor.styles);
+        let to_add_or_replace = self.styles.iter().filter_map(move |(key, value, important)| {
+            let ancestor_value = ancestor_styles
+                .and_then(|styles| Some((styles.get(key)?, styles.is_important(key))));
+            match ancestor_value {
+                None => Some(Patch::Add(key, (value, important))),
+                Some(ancestor_value) if (value, important) != ancestor_value => {
+                    Some(Patch::Replace(key, (value, important)))
+                }
+                _ => None,
+            }
+        });
+        let to_remove = ancestor_styles
+            .into_iter()
+            .flat_map(|styles| styles.iter())
+            .filter(move |(key, _, _)| self.styles.get(key).is_none())
+            .map(|(key, _, _)| Patch::Remove(key));
+
+        to_add_or_replace.chain(to_remove)
+    }
+
+    /// Similar to `diff_attributes`, for the DOM properties.
+    fn diff_properties<'a>(
+        &'a self,
+        ancestor: &'a Option<Box<Self>>,
+    ) -> impl Iterator<Item = Patch<&'a str, &'a PropertyValue>> + 'a {
+        let to_add_or_replace = self.properties.iter().filter_map(move |(key, value)| {
+            match ancestor
+                .as_ref()
+                .and_then(|ancestor| ancestor.properties.get(&**key))
+            {
+                None => Some(Patch::Add(&**key, value)),
+                Some(ancestor_value) if value != ancestor_value => {
+                    Some(Patch::Replace(&**key, value))
+                }
+                _ => None,
+            }
+        });
+        let to_remove = ancestor
+            .iter()
+            .flat_map(|ancestor| ancestor.properties.keys())
+            .filter(move |key| !self.properties.contains_key(&**key))
+            .map(|key| Patch::Remove(&**key));
+
+        to_add_or_replace.chain(to_remove)
+    }
+
     /// Similar to `diff_attributers` except there is only a single `kind`.
     fn diff_kind<'a>(&'a self, ancestor: &'a Option<Box<Self>>) -> Option<Patch<&'a str, ()>> {
         match (
@@ -298,86 +523,199 @@ impl VTag {
         for change in changes {
             match change {
                 Patch::Add(key, value) | Patch::Replace(key, value) => {
-                    element
-                        .set_attribute(&key, &value)
-                        .expect("invalid attribute key");
+                    Backend::set_attribute(element, &key, &value);
+                    if self.element_type == ElementType::Option && key == "selected" {
+                        Backend::set_selected(element, true);
+                    }
                 }
                 Patch::Remove(key) => {
-                    element.remove_attribute(&key).expect("could not remove attribute");
+                    Backend::remove_attribute(element, &key);
+                    if self.element_type == ElementType::Option && key == "selected" {
+                        Backend::set_selected(element, false);
+                    }
                 }
             }
         }
 
-        if self.element_type == ElementType::Button {
-            if let Some(button) = element.dyn_ref::<HtmlButtonElement>() {
-                if let Some(change) = self.diff_kind(ancestor) {
-                    let kind = match change {
-                        Patch::Add(kind, _) | Patch::Replace(kind, _) => kind,
-                        Patch::Remove(_) => "",
-                    };
-                    button.set_type(kind);
+        for change in self.diff_styles(ancestor) {
+            match change {
+                Patch::Add(key, (value, important)) | Patch::Replace(key, (value, important)) => {
+                    Backend::set_style(element, key, value, important);
+                }
+                Patch::Remove(key) => {
+                    Backend::remove_style(element, key);
                 }
             }
         }
 
+        for change in self.diff_properties(ancestor) {
+            match change {
+                Patch::Add(key, value) | Patch::Replace(key, value) => {
+                    Backend::set_property(element, key, value);
+                }
+                Patch::Remove(key) => {
+                    Backend::remove_property(element, key);
+                }
+            }
+        }
+
+        if self.element_type == ElementType::Button {
+            if let Some(change) = self.diff_kind(ancestor) {
+                let kind = match change {
+                    Patch::Add(kind, _) | Patch::Replace(kind, _) => kind,
+                    Patch::Remove(_) => "",
+                };
+                Backend::set_kind(element, kind);
+            }
+        }
+
         // `input` element has extra parameters to control
         // I override behavior of attributes to make it more clear
         // and useful in templates. For example I interpret `checked`
         // attribute as `checked` parameter, not `defaultChecked` as browsers do
         if self.element_type == ElementType::Input {
-            if let Some(input) = element.dyn_ref::<InputElement>() {
-
-                if let Some(change) = self.diff_kind(ancestor) {
-                    let kind = match change {
-                        Patch::Add(kind, _) | Patch::Replace(kind, _) => kind,
-                        Patch::Remove(_) => "",
-                    };
-                    input.set_type(kind)
-                }
-
-                if let Some(change) = self.diff_value(ancestor) {
-                    let raw_value = match change {
-                        Patch::Add(kind, _) | Patch::Replace(kind, _) => kind,
-                        Patch::Remove(_) => "",
-                    };
-                    input.set_value(raw_value);
-                }
+            if let Some(change) = self.diff_kind(ancestor) {
+                let kind = match change {
+                    Patch::Add(kind, _) | Patch::Replace(kind, _) => kind,
+                    Patch::Remove(_) => "",
+                };
+                Backend::set_kind(element, kind);
+            }
 
-                // IMPORTANT! This parameter has to be set every time
-                // to prevent strange behaviour in the browser when the DOM changes
-                set_checked(&input, self.checked);
+            if let Some(change) = self.diff_value(ancestor) {
+                let raw_value = match change {
+                    Patch::Add(kind, _) | Patch::Replace(kind, _) => kind,
+                    Patch::Remove(_) => "",
+                };
+                Backend::set_value(element, raw_value);
             }
+
+            // IMPORTANT! This parameter has to be set every time
+            // to prevent strange behaviour in the browser when the DOM changes
+            Backend::set_checked(element, self.checked);
         } else if self.element_type == ElementType::Textarea {
-            if let Some(tae) = element.dyn_ref::<TextAreaElement>() {
-                if let Some(change) = self.diff_value(ancestor) {
-                    let value = match change {
-                        Patch::Add(kind, _) | Patch::Replace(kind, _) => kind,
-                        Patch::Remove(_) => "",
-                    };
-                    tae.set_value(value);
-                }
+            if let Some(change) = self.diff_value(ancestor) {
+                let value = match change {
+                    Patch::Add(kind, _) | Patch::Replace(kind, _) => kind,
+                    Patch::Remove(_) => "",
+                };
+                Backend::set_value(element, value);
+            }
+        }
+    }
+
+    /// Removes the attributes of an adopted element which the tag doesn't set, the others are
+    /// overwritten when the tag is applied. The attributes written by `render_html` for
+    /// the `kind`, `value`, `checked` and `styles` of the tag are expected too.
+    fn remove_unexpected_attributes(&self, element: &Element) {
+        for name in Backend::attribute_names(element) {
+            let expected = self
+                .attributes
+                .iter()
+                .any(|(key, _)| key.eq_ignore_ascii_case(&name))
+                || match name.to_ascii_lowercase().as_str() {
+                    "type" => self.kind.is_some(),
+                    "value" => self.value.is_some(),
+                    "checked" => self.checked,
+                    "style" => !self.styles.is_empty(),
+                    _ => false,
+                };
+            if !expected {
+                warn!(
+                    "Hydration mismatch: removing unexpected attribute {:?} of <{}>",
+                    name, self.tag
+                );
+                Backend::remove_attribute(element, &name);
             }
         }
     }
 
     fn create_element(&self, parent: &Element) -> Element {
-        if self.tag == "svg"
-            || parent
-                .namespace_uri()
-                .map_or(false, |ns| ns == SVG_NAMESPACE)
+        if self.tag == "svg" || Backend::namespace_uri(parent).is_some_and(|ns| ns == SVG_NAMESPACE)
         {
-            let namespace = SVG_NAMESPACE;
-            document()
-                .create_element_ns(Some(namespace), &self.tag)
-                .expect("can't create namespaced element for vtag")
+            Backend::create_element(&self.tag, Some(SVG_NAMESPACE))
         } else {
-            document()
-                .create_element(&self.tag)
-                .expect("can't create element for vtag")
+            Backend::create_element(&self.tag, None)
         }
     }
 }
 
+/// A listener attached to an element. When the listener has a handler, the element calls it
+/// through a slot, so that it can be replaced without attaching the listener again. A `once`
+/// listener stays fired when its handler is replaced.
+struct AttachedListener {
+    listener: Rc<dyn Listener>,
+    handler: Option<Rc<RefCell<Handler>>>,
+    _handle: ListenerHandle,
+}
+
+impl AttachedListener {
+    fn attach(element: &Element, listener: Rc<dyn Listener>) -> Self {
+        let (event_type, handler) = match listener.handler() {
+            Some(handler) => handler,
+            None => {
+                let handle = listener.attach(element);
+                return AttachedListener {
+                    listener,
+                    handler: None,
+                    _handle: handle,
+                };
+            }
+        };
+        let slot = Rc::new(RefCell::new(handler));
+        let this = element.clone();
+        let handler = slot.clone();
+        let once = listener.options().once;
+        let fired = Cell::new(false);
+        let callback = move |event: web_sys::Event| {
+            if once && fired.replace(true) {
+                return;
+            }
+            let handler = handler.borrow().clone();
+            handler(&this, event)
+        };
+        let options = listener.options().to_event_listener_options(event_type);
+        let handle = Backend::add_listener(element, event_type, options, Box::new(callback));
+        AttachedListener {
+            listener,
+            handler: Some(slot),
+            _handle: handle,
+        }
+    }
+
+    /// Replaces the listener if it handles the same events with the same options, and
+    /// returns `true` if it did.
+    fn update(&mut self, listener: &Rc<dyn Listener>) -> bool {
+        let slot = match &self.handler {
+            Some(slot) => slot,
+            None => return false,
+        };
+        if listener.kind() != self.listener.kind() || listener.options() != self.listener.options()
+        {
+            return false;
+        }
+        if !listener.same_as(self.listener.as_ref()) {
+            match listener.handler() {
+                Some((_, handler)) => {
+                    // The replaced handler is dropped once the slot isn't borrowed anymore
+                    let _replaced = slot.replace(handler);
+                }
+                None => return false,
+            }
+        }
+        self.listener = listener.clone();
+        true
+    }
+}
+
+impl fmt::Debug for AttachedListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AttachedListener")
+            .field("kind", &self.listener.kind())
+            .finish()
+    }
+}
+
 impl VDiff for VTag {
     /// Remove VTag from parent.
     fn detach(&mut self, parent: &Element) {
@@ -388,7 +726,7 @@ impl VDiff for VTag {
 
         // recursively remove its children
         self.children.detach(&node);
-        if parent.remove_child(&node).is_err() {
+        if !Backend::remove_child(parent, &into_node(node)) {
             warn!("Node not found to remove VTag");
         }
     }
@@ -409,7 +747,10 @@ impl VDiff for VTag {
                 VNode::VTag(vtag) if self.tag == vtag.tag => Some(vtag),
                 _ => {
                     let element = self.create_element(parent);
-                    super::insert_node(&element, parent, Some(ancestor.first_node()));
+                    // An ancestor which takes no room in the parent, like a portal, is
+                    // replaced in front of the next sibling
+                    let position = ancestor.first_node().or_else(|| next_sibling.get());
+                    super::insert_node(&element, parent, position);
                     self.reference = Some(element);
                     ancestor.detach(parent);
                     None
@@ -430,36 +771,150 @@ impl VDiff for VTag {
         }
 
         self.apply_diffs(&ancestor_tag);
-        self.recreate_listeners(&mut ancestor_tag);
+        self.recreate_listeners(&mut ancestor_tag, parent_scope.delegation.as_deref());
 
         // Process children
-        let element = self.reference.as_ref().expect("Reference should be set");
+        let element = self.reference.clone().expect("Reference should be set");
+        let ancestor_children = ancestor_tag
+            .as_mut()
+            .map(|ancestor_tag| std::mem::replace(&mut ancestor_tag.children, VList::new()));
         if !self.children.is_empty() {
             self.children.apply(
                 parent_scope,
-                element,
+                &element,
                 NodeRef::default(),
-                ancestor_tag.map(|a| a.children.into()),
+                ancestor_children.map(Into::into),
             );
-        } else if let Some(mut ancestor_tag) = ancestor_tag {
-            ancestor_tag.children.detach(element);
+        } else if let Some(mut ancestor_children) = ancestor_children {
+            ancestor_children.detach(&element);
         }
+        self.apply_selection(ancestor_tag.as_deref());
 
-        let node = element.deref();
-        self.node_ref.set(Some(node.clone()));
+        let node = into_node(element);
+        self.node_ref.set(Some(node));
+        self.node_ref.clone()
+    }
+
+    /// Adopts the `Element` under the cursor when it has the same tag, then sets the
+    /// attributes, attaches the listeners and hydrates the children against its child nodes.
+    fn hydrate(
+        &mut self,
+        parent_scope: &AnyScope,
+        parent: &Element,
+        _next_sibling: NodeRef,
+        cursor: &mut Option<Node>,
+    ) -> NodeRef {
+        let element = cursor
+            .as_ref()
+            .and_then(Backend::as_element)
+            .filter(|element| Backend::tag_name(element).eq_ignore_ascii_case(&self.tag));
+        let element = match element {
+            Some(element) => element,
+            None => {
+                warn!("Hydration mismatch: expected <{}>", self.tag);
+                return self.apply(parent_scope, parent, super::v_dom::cursor_ref(cursor), None);
+            }
+        };
+
+        *cursor = Backend::next_sibling(&into_node(element.clone()));
+        self.reference = Some(element.clone());
+        self.remove_unexpected_attributes(&element);
+        self.apply_diffs(&None);
+        self.recreate_listeners(&mut None, parent_scope.delegation.as_deref());
+
+        // The value of a `textarea` is written as its content
+        if self.element_type != ElementType::Textarea {
+            let mut child_cursor = Backend::first_child(&element);
+            if !self.children.is_empty() {
+                self.children
+                    .hydrate(parent_scope, &element, NodeRef::default(), &mut child_cursor);
+            }
+            super::remove_unclaimed(&element, child_cursor);
+        }
+        self.apply_selection(None);
+
+        self.node_ref.set(Some(into_node(element)));
         self.node_ref.clone()
     }
 }
 
-/// Set `checked` value for the `InputElement`.
-fn set_checked(input: &InputElement, value: bool) {
-    input.set_checked(value)
+impl VRender for VTag {
+    /// Writes the element with its attributes and children. `value`, `kind` and `checked`
+    /// are written as the attributes they override in the DOM, the other properties are
+    /// skipped. Present boolean attributes are written without a value.
+    fn render_html(&self, parent_scope: Option<&AnyScope>, svg: bool, html: &mut String) {
+        let svg = svg || self.tag == "svg";
+        let style = self.styles.to_string();
+        // Attributes are sorted to keep the markup stable between renders
+        let mut attributes: BTreeMap<&str, &str> = self
+            .attributes
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        if let Some(kind) = &self.kind {
+            if self.element_type == ElementType::Input || self.element_type == ElementType::Button
+            {
+                attributes.insert("type", kind);
+            }
+        }
+        if let Some(value) = &self.value {
+            if self.element_type == ElementType::Input {
+                attributes.insert("value", value);
+            }
+        }
+        if !self.styles.is_empty() {
+            attributes.insert("style", &style);
+        }
+        if self.tag == "svg" {
+            attributes.entry("xmlns").or_insert(SVG_NAMESPACE);
+        }
+
+        html.push('<');
+        html.push_str(&self.tag);
+        for (key, value) in attributes {
+            html.push(' ');
+            html.push_str(key);
+            if value.is_empty() && BOOLEAN_ATTRIBUTES.contains(&key) {
+                continue;
+            }
+            html.push_str("=\"");
+            super::v_dom::escape_attribute(value, html);
+            html.push('"');
+        }
+        if self.element_type == ElementType::Input && self.checked {
+            html.push_str(" checked");
+        }
+
+        if svg && self.children.is_empty() {
+            html.push_str("/>");
+            return;
+        }
+        html.push('>');
+        if !svg && VOID_ELEMENTS.contains(&self.tag.to_ascii_lowercase().as_str()) {
+            return;
+        }
+
+        if self.element_type == ElementType::Textarea {
+            if let Some(value) = &self.value {
+                super::v_dom::escape_text(value, html);
+            }
+        } else {
+            // Children of `foreignObject` are back in the HTML namespace
+            let svg = svg && self.tag != "foreignObject";
+            self.children.render_html(parent_scope, svg, html);
+        }
+
+        html.push_str("</");
+        html.push_str(&self.tag);
+        html.push('>');
+    }
 }
 
 impl PartialEq for VTag {
     fn eq(&self, other: &VTag) -> bool {
         self.tag == other.tag
             && self.value == other.value
+            && self.selected_values == other.selected_values
             && self.kind == other.kind
             && self.checked == other.checked
             && self.listeners.len() == other.listeners.len()
@@ -469,6 +924,8 @@ impl PartialEq for VTag {
                 .map(|l| l.kind())
                 .eq(other.listeners.iter().map(|l| l.kind()))
             && self.attributes == other.attributes
+            && self.styles == other.styles
+            && self.properties == other.properties
             && self.children == other.children
     }
 }
@@ -487,3 +944,464 @@ where
         from.clone()
     }
 }
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod native_tests {
+    use super::*;
+    use crate::djed::listener::{onchange, onclick, ondblclick, ChangeData};
+    use crate::djed::{Component, ComponentLink, Html};
+    use crate::djed_dom::{Render, VText};
+    use crate::scheduler::scheduler;
+    use crate::testing::TestRenderer;
+
+    /// Counts the clicks on a button which only handles the first one.
+    struct Once {
+        link: ComponentLink<Self>,
+        clicks: usize,
+    }
+
+    impl Component for Once {
+        type State = ();
+        type Props = ();
+
+        fn create(_: (), link: ComponentLink<Self>) -> Self {
+            Once { link, clicks: 0 }
+        }
+
+        fn update(&mut self, _: ()) -> bool {
+            self.clicks += 1;
+            true
+        }
+
+        fn change(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn view(&self) -> Html {
+            // Every render creates a new callback, which replaces the handler
+            let click = onclick::Wrapper::new(self.link.callback(|_| ())).once();
+            let mut button = VTag::new("button");
+            button.add_listener(Rc::new(click));
+            button.add_child(VText::new(self.clicks.to_string()).into());
+            button.into()
+        }
+    }
+
+    fn click_twice(renderer: TestRenderer<Once>) {
+        let button = renderer.find_by_tag("button").remove(0);
+        renderer.click(&button);
+        assert_eq!(renderer.html(), "<button>1</button>");
+        renderer.click(&button);
+        assert_eq!(renderer.html(), "<button>1</button>");
+    }
+
+    #[test]
+    fn once_listener_stays_fired_across_renders() {
+        click_twice(TestRenderer::mount());
+    }
+
+    #[test]
+    fn delegated_once_listener_stays_fired_across_renders() {
+        click_twice(TestRenderer::mount_with_event_delegation());
+    }
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum Mode {
+        Click(&'static str),
+        DoubleClick,
+        PreventDefault,
+    }
+
+    enum Msg {
+        Switch(Mode),
+        Fired(&'static str),
+    }
+
+    /// Renders a button with a listener which depends on its mode, and logs the listeners
+    /// which fired.
+    struct Switch {
+        link: ComponentLink<Self>,
+        mode: Mode,
+        fired: Vec<&'static str>,
+    }
+
+    impl Component for Switch {
+        type State = Msg;
+        type Props = ();
+
+        fn create(_: (), link: ComponentLink<Self>) -> Self {
+            Switch {
+                link,
+                mode: Mode::Click("first"),
+                fired: Vec::new(),
+            }
+        }
+
+        fn update(&mut self, msg: Msg) -> bool {
+            match msg {
+                Msg::Switch(mode) => self.mode = mode,
+                Msg::Fired(name) => self.fired.push(name),
+            }
+            true
+        }
+
+        fn change(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn view(&self) -> Html {
+            let listener: Rc<dyn Listener> = match self.mode {
+                Mode::Click(name) => {
+                    Rc::new(onclick::Wrapper::new(self.link.callback(move |_| Msg::Fired(name))))
+                }
+                Mode::DoubleClick => Rc::new(ondblclick::Wrapper::new(
+                    self.link.callback(|_| Msg::Fired("double")),
+                )),
+                Mode::PreventDefault => Rc::new(
+                    onclick::Wrapper::new(self.link.callback(|_| Msg::Fired("prevented")))
+                        .prevent_default(),
+                ),
+            };
+            let mut button = VTag::new("button");
+            button.add_listener(listener);
+            button.add_child(VText::new(self.fired.join(" ")).into());
+            button.into()
+        }
+    }
+
+    fn switch(renderer: &TestRenderer<Switch>, mode: Mode) {
+        renderer.link().send_message(Msg::Switch(mode));
+        renderer.flush();
+    }
+
+    #[test]
+    fn new_callback_reuses_the_listener() {
+        let renderer = TestRenderer::<Switch>::mount();
+        let button = renderer.find_by_tag("button").remove(0);
+        let ids = button.listener_ids();
+
+        switch(&renderer, Mode::Click("second"));
+        assert_eq!(button.listener_ids(), ids);
+        renderer.click(&button);
+        assert_eq!(button.text_content(), "second");
+        // Rendering the fired listener gave it a new callback again
+        assert_eq!(button.listener_ids(), ids);
+    }
+
+    #[test]
+    fn other_event_type_attaches_the_listener_again() {
+        let renderer = TestRenderer::<Switch>::mount();
+        let button = renderer.find_by_tag("button").remove(0);
+        let ids = button.listener_ids();
+
+        switch(&renderer, Mode::DoubleClick);
+        assert_ne!(button.listener_ids(), ids);
+        assert_eq!(button.listeners(), vec!["dblclick"]);
+        renderer.click(&button);
+        assert_eq!(button.text_content(), "");
+        renderer.dispatch(&button, "dblclick");
+        assert_eq!(button.text_content(), "double");
+    }
+
+    #[test]
+    fn other_options_attach_the_listener_again() {
+        let renderer = TestRenderer::<Switch>::mount();
+        let button = renderer.find_by_tag("button").remove(0);
+        let ids = button.listener_ids();
+
+        switch(&renderer, Mode::PreventDefault);
+        assert_ne!(button.listener_ids(), ids);
+        assert_eq!(button.listeners(), vec!["click"]);
+        assert!(!renderer.dispatch(&button, "click"));
+        assert_eq!(button.text_content(), "prevented");
+    }
+
+    /// Counts the clicks on a button in a panel.
+    struct Counter {
+        link: ComponentLink<Self>,
+        clicks: usize,
+    }
+
+    impl Component for Counter {
+        type State = ();
+        type Props = ();
+
+        fn create(_: (), link: ComponentLink<Self>) -> Self {
+            Counter { link, clicks: 0 }
+        }
+
+        fn update(&mut self, _: ()) -> bool {
+            self.clicks += 1;
+            true
+        }
+
+        fn change(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn view(&self) -> Html {
+            let mut button = VTag::new("button");
+            button.add_listener(Rc::new(onclick::Wrapper::new(self.link.callback(|_| ()))));
+            button.add_child(VText::new(self.clicks.to_string()).into());
+            let mut panel = VTag::new("div");
+            panel.add_attribute("class", &"panel");
+            panel.add_child(button.into());
+            panel.into()
+        }
+    }
+
+    fn server_element(parent: &Element, tag: &str, attributes: &[(&str, &str)]) -> Element {
+        let element = Backend::create_element(tag, None);
+        for (name, value) in attributes {
+            Backend::set_attribute(&element, name, value);
+        }
+        Backend::insert_before(parent, &element, None);
+        element
+    }
+
+    fn server_text(parent: &Element, text: &str) {
+        Backend::insert_before(parent, &Backend::create_text(text), None);
+    }
+
+    fn hydrate(root: &Element) {
+        Render::<Counter>::new().hydrate(root.clone());
+        scheduler().run_until_idle();
+    }
+
+    #[test]
+    fn hydrate_removes_unexpected_attributes() {
+        let root = Backend::create_element("body", None);
+        let panel = server_element(&root, "div", &[("class", "stale"), ("data-server", "1")]);
+        let button = server_element(&panel, "button", &[("disabled", "")]);
+        server_text(&button, "0");
+
+        hydrate(&root);
+        assert_eq!(
+            root.inner_html(),
+            "<div class=\"panel\"><button>0</button></div>"
+        );
+        // The server-rendered nodes were adopted, with their listeners
+        assert_eq!(root.children(), vec![panel]);
+        button.dispatch_event("click");
+        scheduler().run_until_idle();
+        assert_eq!(button.text_content(), "1");
+    }
+
+    #[test]
+    fn hydrate_recovers_from_mismatched_nodes() {
+        let root = Backend::create_element("body", None);
+        let stale = server_element(&root, "p", &[("class", "panel")]);
+        server_text(&stale, "stale");
+        server_element(&root, "span", &[]);
+
+        hydrate(&root);
+        assert_eq!(
+            root.inner_html(),
+            "<div class=\"panel\"><button>0</button></div>"
+        );
+        let button = root.children()[0].children()[0].clone();
+        button.dispatch_event("click");
+        scheduler().run_until_idle();
+        assert_eq!(button.text_content(), "1");
+    }
+
+    enum Pick {
+        Fruit(String),
+        Colors(Vec<String>),
+        Tick,
+    }
+
+    /// Controls a single and a multiple select, refusing "cherry" as the fruit.
+    struct Picker {
+        link: ComponentLink<Self>,
+        fruit: String,
+        colors: Vec<String>,
+        ticks: usize,
+    }
+
+    fn options(select: &mut VTag, values: &[&str]) {
+        for value in values {
+            let mut option = VTag::new("option");
+            option.add_attribute("value", value);
+            option.add_child(VText::new(value.to_string()).into());
+            select.add_child(option.into());
+        }
+    }
+
+    impl Component for Picker {
+        type State = Pick;
+        type Props = ();
+
+        fn create(_: (), link: ComponentLink<Self>) -> Self {
+            Picker {
+                link,
+                fruit: "banana".to_string(),
+                colors: vec!["red".to_string()],
+                ticks: 0,
+            }
+        }
+
+        fn update(&mut self, pick: Pick) -> bool {
+            match pick {
+                Pick::Fruit(fruit) if fruit != "cherry" => self.fruit = fruit,
+                Pick::Fruit(_) => {}
+                Pick::Colors(colors) => self.colors = colors,
+                Pick::Tick => self.ticks += 1,
+            }
+            true
+        }
+
+        fn change(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn view(&self) -> Html {
+            let mut fruit = VTag::new("select");
+            fruit.add_attribute("id", &"fruit");
+            fruit.set_value(&self.fruit);
+            let pick = self
+                .link
+                .callback(|data: ChangeData| Pick::Fruit(data.value().unwrap_or_default()));
+            fruit.add_listener(Rc::new(onchange::Wrapper::new(pick)));
+            options(&mut fruit, &["apple", "banana", "cherry"]);
+
+            let mut colors = VTag::new("select");
+            colors.add_attribute("id", &"colors");
+            colors.add_attribute("multiple", &"");
+            colors.set_selected_values(&self.colors);
+            let pick = self
+                .link
+                .callback(|data: ChangeData| Pick::Colors(data.values()));
+            colors.add_listener(Rc::new(onchange::Wrapper::new(pick)));
+            options(&mut colors, &["red", "green", "blue"]);
+
+            let mut form = VTag::new("form");
+            form.add_child(fruit.into());
+            form.add_child(colors.into());
+            form.add_child(VText::new(self.ticks.to_string()).into());
+            form.into()
+        }
+    }
+
+    fn selected(renderer: &TestRenderer<Picker>, id: &str) -> Vec<String> {
+        let select = renderer.find_by_id(id).expect("select expected");
+        select
+            .children()
+            .iter()
+            .filter(|option| option.selected())
+            .map(|option| option.text_content())
+            .collect()
+    }
+
+    #[test]
+    fn rerender_keeps_the_selection() {
+        let renderer = TestRenderer::<Picker>::mount();
+        assert_eq!(selected(&renderer, "fruit"), vec!["banana"]);
+        assert_eq!(selected(&renderer, "colors"), vec!["red"]);
+
+        let fruit = renderer.find_by_id("fruit").unwrap();
+        renderer.select(&fruit, &["apple"]);
+        let colors = renderer.find_by_id("colors").unwrap();
+        renderer.select(&colors, &["green", "blue"]);
+        renderer.link().send_message(Pick::Tick);
+        renderer.flush();
+        assert_eq!(selected(&renderer, "fruit"), vec!["apple"]);
+        assert_eq!(selected(&renderer, "colors"), vec!["green", "blue"]);
+
+        // The refused selection is replaced by the controlled one
+        renderer.select(&fruit, &["cherry"]);
+        assert_eq!(selected(&renderer, "fruit"), vec!["apple"]);
+    }
+
+    fn tag(name: &str, children: Vec<VNode>) -> VTag {
+        let mut tag = VTag::new(name.to_string());
+        for child in children {
+            tag.add_child(child);
+        }
+        tag
+    }
+
+    fn text(text: &str) -> VNode {
+        VText::new(text.to_string()).into()
+    }
+
+    #[test]
+    fn render_escapes_text_and_attributes() {
+        let mut div = tag("div", vec![text("1 < 2 & \"3\" > 0")]);
+        div.add_attribute("title", &"a \"b\" <c> & d");
+        assert_eq!(
+            VNode::from(div).render_to_string(),
+            "<div title=\"a &quot;b&quot; &lt;c&gt; &amp; d\">1 &lt; 2 &amp; \"3\" &gt; 0</div>"
+        );
+    }
+
+    #[test]
+    fn render_void_elements_without_closing_tags() {
+        let mut input = tag("input", Vec::new());
+        input.set_kind(&"checkbox");
+        input.set_value(&"a\"b");
+        input.set_checked(true);
+        input.set_boolean_attribute("disabled", true);
+        let mut image = tag("img", Vec::new());
+        image.add_attribute("src", &"a.png");
+        let br = tag("br", Vec::new());
+        let div = tag("div", vec![br.into(), input.into(), image.into()]);
+        assert_eq!(
+            VNode::from(div).render_to_string(),
+            "<div><br><input disabled type=\"checkbox\" value=\"a&quot;b\" checked>\
+             <img src=\"a.png\"></div>"
+        );
+    }
+
+    #[test]
+    fn render_svg_elements_in_their_namespace() {
+        let mut circle = tag("circle", Vec::new());
+        circle.add_attribute("r", &"1");
+        let object = tag("foreignObject", vec![tag("br", Vec::new()).into()]);
+        let svg = tag("svg", vec![circle.into(), object.into()]);
+        assert_eq!(
+            VNode::from(tag("p", vec![svg.into()])).render_to_string(),
+            "<p><svg xmlns=\"http://www.w3.org/2000/svg\"><circle r=\"1\"/>\
+             <foreignObject><br></foreignObject></svg></p>"
+        );
+    }
+
+    #[test]
+    fn render_the_value_of_textareas_as_their_content() {
+        let mut textarea = tag("textarea", vec![text("ignored")]);
+        textarea.set_value(&"</textarea> & more");
+        assert_eq!(
+            VNode::from(textarea).render_to_string(),
+            "<textarea>&lt;/textarea&gt; &amp; more</textarea>"
+        );
+    }
+
+    fn styled(styles: &str) -> Option<Box<VTag>> {
+        let mut div = VTag::new("div");
+        div.add_styles(styles);
+        Some(Box::new(div))
+    }
+
+    #[test]
+    fn diff_styles_by_property() {
+        let ancestor = styled("a: 1; b: 2; c: 3");
+        let tag = styled("b: 2; a: 4 !important; d: 5").unwrap();
+        assert_eq!(
+            tag.diff_styles(&ancestor).collect::<Vec<_>>(),
+            vec![
+                Patch::Replace("a", ("4", true)),
+                Patch::Add("d", ("5", false)),
+                Patch::Remove("c"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reordered_styles_are_not_patched() {
+        let ancestor = styled("a: 1; b: 2");
+        let tag = styled("b: 2; a: 1").unwrap();
+        assert_eq!(tag.diff_styles(&ancestor).count(), 0);
+        assert!(*tag == **ancestor.as_ref().unwrap());
+    }
+}
+
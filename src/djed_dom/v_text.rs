@@ -1,9 +1,8 @@
-use super::{VDiff, VNode};
+use super::{VDiff, VNode, VRender};
 use crate::djed::{AnyScope, NodeRef};
-use crate::utils::document;
 use log::warn;
 use std::cmp::PartialEq;
-use web_sys::{Element, Text as TextNode};
+use super::backend::{into_node, Backend, DomBackend, Element, Node, Text as TextNode};
 
 /// A type for a virtual
 /// [`TextNode`](https://developer.mozilla.org/en-US/docs/Web/API/Document/createTextNode)
@@ -33,7 +32,7 @@ impl VDiff for VText {
             .reference
             .take()
             .expect("tried to remove not rendered VText from DOM");
-        if parent.remove_child(&node).is_err() {
+        if !Backend::remove_child(parent, &into_node(node)) {
             warn!("Node not found to remove VText");
         }
     }
@@ -54,19 +53,62 @@ impl VDiff for VText {
                     .clone()
                     .expect("Rendered VText nodes should have a ref");
                 if self.text != vtext.text {
-                    text_node.set_node_value(Some(&self.text));
+                    Backend::set_text(&text_node, &self.text);
                 }
 
-                return NodeRef::new(text_node.into());
+                return NodeRef::new(into_node(text_node));
             }
 
             ancestor.detach(parent);
         }
 
-        let text_node = document().create_text_node(&self.text);
+        let text_node = Backend::create_text(&self.text);
         super::insert_node(&text_node, parent, next_sibling.get());
         self.reference = Some(text_node.clone());
-        NodeRef::new(text_node.into())
+        NodeRef::new(into_node(text_node))
+    }
+
+    /// Adopts the `TextNode` under the cursor. Adjacent texts are merged into a single node
+    /// by the HTML parser, so the node is split when it starts with the expected text.
+    fn hydrate(
+        &mut self,
+        parent_scope: &AnyScope,
+        parent: &Element,
+        _next_sibling: NodeRef,
+        cursor: &mut Option<Node>,
+    ) -> NodeRef {
+        // Empty texts are not part of the markup
+        if self.text.is_empty() {
+            return self.apply(parent_scope, parent, super::v_dom::cursor_ref(cursor), None);
+        }
+
+        let text_node = match cursor.as_ref().and_then(Backend::as_text) {
+            Some(text_node) => text_node,
+            None => {
+                warn!("Hydration mismatch: expected text {:?}", self.text);
+                return self.apply(parent_scope, parent, super::v_dom::cursor_ref(cursor), None);
+            }
+        };
+
+        let data = Backend::text(&text_node);
+        if data != self.text {
+            if data.starts_with(&self.text) {
+                Backend::split_text(&text_node, &self.text);
+            } else {
+                warn!("Hydration mismatch: expected text {:?}, found {:?}", self.text, data);
+                Backend::set_text(&text_node, &self.text);
+            }
+        }
+
+        *cursor = Backend::next_sibling(&into_node(text_node.clone()));
+        self.reference = Some(text_node.clone());
+        NodeRef::new(into_node(text_node))
+    }
+}
+
+impl VRender for VText {
+    fn render_html(&self, _parent_scope: Option<&AnyScope>, _svg: bool, html: &mut String) {
+        super::v_dom::escape_text(&self.text, html);
     }
 }
 
@@ -2,11 +2,14 @@ pub mod djed;
 pub mod djed_dom;
 pub mod callback;
 pub mod scheduler;
+pub mod profiler;
 pub mod utils;
 pub mod djed_agent;
 pub mod djed_format;
 pub mod djed_services;
 pub mod effect;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod testing;
 
 
 use proc_macro_hack::proc_macro_hack;
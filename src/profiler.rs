@@ -0,0 +1,358 @@
+//! Profiling of the lifecycle of the components.
+//!
+//! Once profiling is enabled with [`Scheduler::set_profiling`](crate::scheduler::Scheduler::set_profiling),
+//! the scheduler counts and times the `update`, `view`, diff-apply and `rendered` phases of
+//! every component, and the causes of their renders. In the browser every phase is also
+//! added to the performance timeline with `performance.mark` and `performance.measure`.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// A phase of the lifecycle of a component.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Phase {
+    /// The `update` or `change` method handling a message or properties.
+    Update,
+    /// The `view` method.
+    View,
+    /// The diff of the new view applied to the DOM.
+    Apply,
+    /// The `rendered` method.
+    Rendered,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Phase::Update => "update",
+            Phase::View => "view",
+            Phase::Apply => "apply",
+            Phase::Rendered => "rendered",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The reason a component rendered its view again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RenderCause {
+    /// The component handled a message.
+    Message,
+    /// The component handled a batch of messages.
+    Batch,
+    /// The properties of the component changed.
+    Props,
+    /// The component was rendered unconditionally, for example when it was mounted.
+    Force,
+}
+
+/// The number of runs and the durations of a phase, in milliseconds.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PhaseStats {
+    /// The number of times the phase ran.
+    pub count: usize,
+    /// The total duration of the phase.
+    pub total: f64,
+    /// The longest duration of the phase.
+    pub max: f64,
+}
+
+impl PhaseStats {
+    /// Returns the average duration of the phase.
+    pub fn mean(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.total / self.count as f64
+        }
+    }
+
+    fn add(&mut self, duration: f64) {
+        self.count += 1;
+        self.total += duration;
+        self.max = self.max.max(duration);
+    }
+}
+
+/// The profile of a component instance.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ComponentProfile {
+    /// The type name of the component.
+    pub component: &'static str,
+    /// The identifier of the scope of the component, which tells apart the instances of a
+    /// component type. The scopes are numbered in the order they are created, so an
+    /// identifier is never reused by another instance.
+    pub scope_id: usize,
+    /// The statistics of each phase which ran.
+    pub phases: BTreeMap<Phase, PhaseStats>,
+    /// The number of renders for each cause.
+    pub causes: BTreeMap<RenderCause, usize>,
+}
+
+impl ComponentProfile {
+    /// Returns the statistics of a `phase`.
+    pub fn phase(&self, phase: Phase) -> PhaseStats {
+        self.phases.get(&phase).copied().unwrap_or_default()
+    }
+
+    /// Returns the number of renders caused by `cause`.
+    pub fn renders(&self, cause: RenderCause) -> usize {
+        self.causes.get(&cause).copied().unwrap_or_default()
+    }
+}
+
+impl fmt::Display for ComponentProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.component, self.scope_id)?;
+        for (phase, stats) in &self.phases {
+            write!(
+                f,
+                " {}: {} in {:.3}ms (max {:.3}ms)",
+                phase, stats.count, stats.total, stats.max
+            )?;
+        }
+        for (cause, count) in &self.causes {
+            write!(f, " {:?}: {}", cause, count)?;
+        }
+        Ok(())
+    }
+}
+
+/// The profiles of the components, by component type and scope.
+#[derive(Debug, Default)]
+pub(crate) struct Profiler {
+    profiles: BTreeMap<(&'static str, usize), ComponentProfile>,
+}
+
+impl Profiler {
+    fn profile(&mut self, component: &'static str, scope_id: usize) -> &mut ComponentProfile {
+        self.profiles
+            .entry((component, scope_id))
+            .or_insert_with(|| ComponentProfile {
+                component,
+                scope_id,
+                ..ComponentProfile::default()
+            })
+    }
+
+    pub(crate) fn add_phase(
+        &mut self,
+        component: &'static str,
+        scope_id: usize,
+        phase: Phase,
+        duration: f64,
+    ) {
+        self.profile(component, scope_id)
+            .phases
+            .entry(phase)
+            .or_default()
+            .add(duration);
+    }
+
+    pub(crate) fn add_render(
+        &mut self,
+        component: &'static str,
+        scope_id: usize,
+        cause: RenderCause,
+    ) {
+        *self
+            .profile(component, scope_id)
+            .causes
+            .entry(cause)
+            .or_default() += 1;
+    }
+
+    pub(crate) fn profiles(&self) -> Vec<ComponentProfile> {
+        self.profiles.values().cloned().collect()
+    }
+}
+
+/// Returns the current time in milliseconds.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn now() -> f64 {
+    match web_sys::window().and_then(|window| window.performance()) {
+        Some(performance) => performance.now(),
+        None => js_sys::Date::now(),
+    }
+}
+
+/// Returns the current time in milliseconds.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn now() -> f64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |time| time.as_secs_f64() * 1000.0)
+}
+
+/// Adds a mark at the start of a phase to the performance timeline.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn mark_start(name: &str) {
+    if let Some(performance) = web_sys::window().and_then(|window| window.performance()) {
+        let _ = performance.mark(&format!("{} start", name));
+    }
+}
+
+/// Adds a mark at the end of a phase and the measure of the phase to the performance
+/// timeline.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn measure(name: &str) {
+    if let Some(performance) = web_sys::window().and_then(|window| window.performance()) {
+        let start = format!("{} start", name);
+        let end = format!("{} end", name);
+        let _ = performance.mark(&end);
+        let _ = performance.measure_with_start_mark_and_end_mark(name, &start, &end);
+        performance.clear_marks_with_mark_name(&start);
+        performance.clear_marks_with_mark_name(&end);
+    }
+}
+
+/// There is no performance timeline outside of the browser.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn mark_start(_name: &str) {}
+
+/// There is no performance timeline outside of the browser.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn measure(_name: &str) {}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::djed::{Component, ComponentLink, Html, NodeRef, ShouldRender};
+    use crate::djed_dom::{VComp, VTag, VText};
+    use crate::scheduler::scheduler;
+    use crate::testing::TestRenderer;
+
+    struct Item;
+
+    impl Component for Item {
+        type State = ();
+        type Props = ();
+
+        fn create(_: (), _: ComponentLink<Self>) -> Self {
+            Item
+        }
+
+        fn update(&mut self, _: ()) -> ShouldRender {
+            false
+        }
+
+        fn change(&mut self, _: ()) -> ShouldRender {
+            true
+        }
+
+        fn view(&self) -> Html {
+            let mut item = VTag::new("li");
+            item.add_child(VText::new("item".to_string()).into());
+            item.into()
+        }
+    }
+
+    /// Renders as many items as the last number it received.
+    struct Items(usize);
+
+    impl Component for Items {
+        type State = usize;
+        type Props = ();
+
+        fn create(_: (), _: ComponentLink<Self>) -> Self {
+            Items(1)
+        }
+
+        fn update(&mut self, count: usize) -> ShouldRender {
+            self.0 = count;
+            true
+        }
+
+        fn change(&mut self, _: ()) -> ShouldRender {
+            false
+        }
+
+        fn view(&self) -> Html {
+            let mut list = VTag::new("ul");
+            for _ in 0..self.0 {
+                list.add_child(VComp::new::<Item>((), NodeRef::default(), None).into());
+            }
+            list.into()
+        }
+    }
+
+    fn profiles_of<COMP: Component>() -> Vec<ComponentProfile> {
+        let component = std::any::type_name::<COMP>();
+        scheduler()
+            .profiles()
+            .into_iter()
+            .filter(|profile| profile.component == component)
+            .collect()
+    }
+
+    #[test]
+    fn profiles_count_the_phases_and_causes_of_each_component() {
+        let scheduler = scheduler();
+        scheduler.set_profiling(true);
+        let renderer = TestRenderer::<Items>::mount();
+        renderer.link().send_message(2_usize);
+        renderer.flush();
+        renderer.link().send_message_batch(vec![3, 2]);
+        renderer.flush();
+        let items = profiles_of::<Items>();
+        let item = profiles_of::<Item>();
+        scheduler.set_profiling(false);
+
+        assert_eq!(items.len(), 1);
+        let items = &items[0];
+        assert_eq!(items.renders(RenderCause::Force), 1);
+        assert_eq!(items.renders(RenderCause::Message), 1);
+        assert_eq!(items.renders(RenderCause::Batch), 1);
+        assert_eq!(items.renders(RenderCause::Props), 0);
+        for phase in &[Phase::Update, Phase::View, Phase::Apply, Phase::Rendered] {
+            assert_eq!(items.phase(*phase).count, 3, "{}", phase);
+        }
+
+        // The first item was mounted, then changed by both renders of the list
+        assert_eq!(item.len(), 2);
+        assert_eq!(item[0].renders(RenderCause::Force), 1);
+        assert_eq!(item[0].renders(RenderCause::Props), 2);
+        assert_eq!(item[1].renders(RenderCause::Force), 1);
+        assert_eq!(item[1].renders(RenderCause::Props), 1);
+        assert!(item[0].scope_id < item[1].scope_id);
+    }
+
+    #[test]
+    fn profiles_tell_apart_components_created_after_others_were_destroyed() {
+        let scheduler = scheduler();
+        scheduler.set_profiling(true);
+        let renderer = TestRenderer::<Items>::mount();
+        for count in &[0_usize, 1, 0, 1] {
+            renderer.link().send_message(*count);
+            renderer.flush();
+        }
+        let item = profiles_of::<Item>();
+        scheduler.set_profiling(false);
+
+        assert_eq!(item.len(), 3);
+        assert!(item
+            .iter()
+            .all(|profile| profile.renders(RenderCause::Force) == 1));
+    }
+
+    #[test]
+    fn profiles_display_their_phases_and_causes() {
+        let mut profile = ComponentProfile {
+            component: "App",
+            scope_id: 3,
+            ..ComponentProfile::default()
+        };
+        let view = profile.phases.entry(Phase::View).or_default();
+        view.add(0.5);
+        view.add(1.0);
+        profile.causes.insert(RenderCause::Message, 2);
+
+        assert_eq!(profile.phase(Phase::View).mean(), 0.75);
+        assert_eq!(profile.phase(Phase::Update), PhaseStats::default());
+        assert_eq!(
+            profile.to_string(),
+            "App (3) view: 2 in 1.500ms (max 1.000ms) Message: 2"
+        );
+    }
+}
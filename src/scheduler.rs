@@ -1,6 +1,9 @@
-use std::cell::RefCell;
-use std::collections::VecDeque;
+use crate::profiler::{now, ComponentProfile, Phase, Profiler, RenderCause};
+use std::cell::{Cell, RefCell};
+use std::collections::{HashSet, VecDeque};
 use std::rc::Rc;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::{closure::Closure, JsCast};
 
 pub type Shared<T> = Rc<RefCell<T>>;
 
@@ -17,6 +20,67 @@ pub fn scheduler() -> Rc<Scheduler> {
 pub trait Runnable {
     /// Runs a routine with a context instance.
     fn run(self: Box<Self>);
+
+    /// Returns the type name of the component the routine runs for, if any. It is stored
+    /// in the records of the scheduler.
+    fn component_name(&self) -> Option<&'static str> {
+        None
+    }
+}
+
+/// The kind of a routine run by the scheduler.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunnableKind {
+    Destroy,
+    Create,
+    Update,
+    Render,
+    /// A routine which doesn't belong to a component, like the tasks of agents.
+    Task,
+}
+
+impl From<&ComponentRunnableType> for RunnableKind {
+    fn from(run_type: &ComponentRunnableType) -> Self {
+        match run_type {
+            ComponentRunnableType::Destroy => RunnableKind::Destroy,
+            ComponentRunnableType::Create => RunnableKind::Create,
+            ComponentRunnableType::Update => RunnableKind::Update,
+            ComponentRunnableType::Render => RunnableKind::Render,
+        }
+    }
+}
+
+/// A routine run by the scheduler while it was recording, see [`Scheduler::set_recording`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RunRecord {
+    /// The kind of the routine.
+    pub kind: RunnableKind,
+    /// The type name of the component the routine ran for, if any.
+    pub component: Option<&'static str>,
+}
+
+/// A routine which records itself when it is run.
+struct Recorded {
+    kind: RunnableKind,
+    runnable: Box<dyn Runnable>,
+    records: Shared<Option<Vec<RunRecord>>>,
+}
+
+impl Runnable for Recorded {
+    fn run(self: Box<Self>) {
+        let record = RunRecord {
+            kind: self.kind,
+            component: self.runnable.component_name(),
+        };
+        if let Some(records) = self.records.borrow_mut().as_mut() {
+            records.push(record);
+        }
+        self.runnable.run();
+    }
+
+    fn component_name(&self) -> Option<&'static str> {
+        self.runnable.component_name()
+    }
 }
 
 /// This is a global scheduler suitable to schedule and run any tasks.
@@ -24,8 +88,21 @@ pub trait Runnable {
 pub struct Scheduler {
     /// This lock is used to prevent recursion in [Scheduler#start()](Scheduler#start())
     lock: Rc<RefCell<()>>,
-    main: Shared<VecDeque<Box<dyn Runnable>>>,
     component: ComponentScheduler,
+    /// The tasks of each priority, from the highest to the lowest. The updates of the
+    /// `UserInput` and `Normal` lanes run before any render, and the `Idle` updates after the
+    /// `UserInput` renders
+    lanes: [Lane; 3],
+    /// The priority of the tasks which are pushed without one
+    priority: Rc<Cell<Priority>>,
+    /// The time in milliseconds after which `start` yields to the browser
+    budget: Rc<Cell<Option<f64>>>,
+    continuation_requested: Rc<Cell<bool>>,
+    frame: FrameScheduler,
+    /// Whether the tasks only run when they are driven manually
+    manual: Rc<Cell<bool>>,
+    records: Shared<Option<Vec<RunRecord>>>,
+    profiler: Shared<Option<Profiler>>,
 }
 
 pub enum ComponentRunnableType {
@@ -35,15 +112,36 @@ pub enum ComponentRunnableType {
     Render,
 }
 
+/// The priority of a task. Tasks with a higher priority run first, and the tasks handling
+/// user input are never delayed to yield to the browser.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    /// Tasks started by user input, like the messages sent by the listeners of elements.
+    UserInput,
+    /// Tasks which aren't started by user input.
+    #[default]
+    Normal,
+    /// Background tasks, which only run when there is nothing else to do.
+    Idle,
+}
+
+impl Priority {
+    fn index(self) -> usize {
+        match self {
+            Priority::UserInput => 0,
+            Priority::Normal => 1,
+            Priority::Idle => 2,
+        }
+    }
+}
+
+/// The components are created and destroyed before any update, whatever their priority,
+/// so that they always exist when they are updated.
 #[derive(Clone)]
 struct ComponentScheduler {
     // Queues
     destroy: Shared<VecDeque<Box<dyn Runnable>>>,
     create: Shared<VecDeque<Box<dyn Runnable>>>,
-    update: Shared<VecDeque<Box<dyn Runnable>>>,
-
-    // Stack
-    render: Shared<Vec<Box<dyn Runnable>>>,
 }
 
 impl ComponentScheduler {
@@ -51,16 +149,80 @@ impl ComponentScheduler {
         ComponentScheduler {
             destroy: Rc::new(RefCell::new(VecDeque::new())),
             create: Rc::new(RefCell::new(VecDeque::new())),
-            update: Rc::new(RefCell::new(VecDeque::new())),
-            render: Rc::new(RefCell::new(Vec::new())),
         }
     }
 
     fn next_runnable(&self) -> Option<Box<dyn Runnable>> {
         None.or_else(|| self.destroy.borrow_mut().pop_front())
             .or_else(|| self.create.borrow_mut().pop_front())
-            .or_else(|| self.update.borrow_mut().pop_front())
+    }
+
+}
+
+/// The tasks of a priority.
+#[derive(Clone)]
+struct Lane {
+    // Queues
+    update: Shared<VecDeque<Box<dyn Runnable>>>,
+    main: Shared<VecDeque<Box<dyn Runnable>>>,
+
+    // Stack
+    render: Shared<Vec<Box<dyn Runnable>>>,
+}
+
+impl Lane {
+    fn new() -> Self {
+        Lane {
+            update: Rc::new(RefCell::new(VecDeque::new())),
+            main: Rc::new(RefCell::new(VecDeque::new())),
+            render: Rc::new(RefCell::new(Vec::new())),
+        }
+    }
+
+    fn next_runnable(&self) -> Option<Box<dyn Runnable>> {
+        None.or_else(|| self.update.borrow_mut().pop_front())
             .or_else(|| self.render.borrow_mut().pop())
+            .or_else(|| self.main.borrow_mut().pop_front())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn len(&self) -> usize {
+        self.update.borrow().len() + self.render.borrow().len() + self.main.borrow().len()
+    }
+}
+
+/// The renders of the components which are batched until the next animation frame.
+#[derive(Clone)]
+struct FrameScheduler {
+    enabled: Rc<Cell<bool>>,
+    requested: Rc<Cell<bool>>,
+    /// Whether the renders of a frame are running
+    rendering: Rc<Cell<bool>>,
+    /// The components which were rendered in the current frame
+    rendered: Shared<HashSet<usize>>,
+    pending: Shared<Vec<PendingRender>>,
+}
+
+/// A render batched until the next frame.
+struct PendingRender {
+    /// Identifies the rendered component
+    key: usize,
+    priority: Priority,
+    runnable: Box<dyn Runnable>,
+}
+
+impl FrameScheduler {
+    fn new() -> Self {
+        FrameScheduler {
+            enabled: Rc::new(Cell::new(false)),
+            requested: Rc::new(Cell::new(false)),
+            rendering: Rc::new(Cell::new(false)),
+            rendered: Rc::new(RefCell::new(HashSet::new())),
+            pending: Rc::new(RefCell::new(Vec::new())),
+        }
     }
 }
 
@@ -68,41 +230,496 @@ impl Scheduler {
     fn new() -> Self {
         Scheduler {
             lock: Rc::new(RefCell::new(())),
-            main: Rc::new(RefCell::new(VecDeque::new())),
             component: ComponentScheduler::new(),
+            lanes: [Lane::new(), Lane::new(), Lane::new()],
+            priority: Rc::new(Cell::new(Priority::default())),
+            budget: Rc::new(Cell::new(None)),
+            continuation_requested: Rc::new(Cell::new(false)),
+            frame: FrameScheduler::new(),
+            manual: Rc::new(Cell::new(false)),
+            records: Rc::new(RefCell::new(None)),
+            profiler: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    /// Starts or stops profiling the components. See the [`profiler`](crate::profiler)
+    /// module.
+    pub fn set_profiling(&self, enabled: bool) {
+        let mut profiler = self.profiler.borrow_mut();
+        if enabled != profiler.is_some() {
+            *profiler = if enabled {
+                Some(Profiler::default())
+            } else {
+                None
+            };
         }
     }
 
+    /// Returns the profiles of the components since the profiling started or the profiles
+    /// were cleared, ordered by component type and scope.
+    pub fn profiles(&self) -> Vec<ComponentProfile> {
+        self.profiler
+            .borrow()
+            .as_ref()
+            .map(Profiler::profiles)
+            .unwrap_or_default()
+    }
+
+    /// Clears the profiles of the components.
+    pub fn clear_profiles(&self) {
+        if let Some(profiler) = self.profiler.borrow_mut().as_mut() {
+            *profiler = Profiler::default();
+        }
+    }
+
+    /// Runs a `phase` of a component, and times it if the components are profiled.
+    pub(crate) fn profile<R>(
+        &self,
+        component: &'static str,
+        scope_id: usize,
+        phase: Phase,
+        function: impl FnOnce() -> R,
+    ) -> R {
+        if self.profiler.borrow().is_none() {
+            return function();
+        }
+
+        let name = format!("{} ({}) {}", component, scope_id, phase);
+        crate::profiler::mark_start(&name);
+        let started = now();
+        let result = function();
+        let duration = now() - started;
+        crate::profiler::measure(&name);
+        if let Some(profiler) = self.profiler.borrow_mut().as_mut() {
+            profiler.add_phase(component, scope_id, phase, duration);
+        }
+        result
+    }
+
+    /// Counts a render of a component, if the components are profiled.
+    pub(crate) fn profile_render(
+        &self,
+        component: &'static str,
+        scope_id: usize,
+        cause: RenderCause,
+    ) {
+        if let Some(profiler) = self.profiler.borrow_mut().as_mut() {
+            profiler.add_render(component, scope_id, cause);
+        }
+    }
+
+    /// Makes the scheduler driven manually, so that the pushed tasks wait until
+    /// [`step`](Self::step) or [`run_until_idle`](Self::run_until_idle) is called instead of
+    /// running right away. Tests use it to check the order of the lifecycle methods.
+    pub fn set_manual(&self, manual: bool) {
+        self.manual.set(manual);
+    }
+
+    /// Runs the next task, and returns `false` if there was none.
+    pub fn step(&self) -> bool {
+        self.run(Some(1), false) == 1
+    }
+
+    /// Runs the tasks until there are none left, ignoring the time budget, and returns how
+    /// many ran.
+    pub fn run_until_idle(&self) -> usize {
+        self.run(None, false)
+    }
+
+    /// Returns the number of tasks waiting to run, without the renders batched until the
+    /// next frame.
+    pub fn pending_count(&self) -> usize {
+        self.component.destroy.borrow().len()
+            + self.component.create.borrow().len()
+            + self.lanes.iter().map(Lane::len).sum::<usize>()
+    }
+
+    /// Starts or stops recording the tasks which run. The records are returned by
+    /// [`take_records`](Self::take_records).
+    pub fn set_recording(&self, recording: bool) {
+        let mut records = self.records.borrow_mut();
+        if recording != records.is_some() {
+            *records = if recording { Some(Vec::new()) } else { None };
+        }
+    }
+
+    /// Returns the tasks which ran since the recording started or the records were last
+    /// taken.
+    pub fn take_records(&self) -> Vec<RunRecord> {
+        self.records
+            .borrow_mut()
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// Wraps the `runnable` to record it when it runs, if the scheduler is recording.
+    fn record(&self, kind: RunnableKind, runnable: Box<dyn Runnable>) -> Box<dyn Runnable> {
+        if self.records.borrow().is_none() {
+            return runnable;
+        }
+        Box::new(Recorded {
+            kind,
+            runnable,
+            records: self.records.clone(),
+        })
+    }
+
+    /// Returns the priority of the tasks which are pushed without one. It is the priority of
+    /// the task which is running, so that the tasks it pushes inherit it.
+    pub fn priority(&self) -> Priority {
+        self.priority.get()
+    }
+
+    /// Calls `function` with `priority` as the priority of the tasks which are pushed without
+    /// one, for example to send a message to a component in the background:
+    ///
+    /// ```ignore
+    /// scheduler().with_priority(Priority::Idle, || link.send_message(Msg::Refresh));
+    /// ```
+    pub fn with_priority<R>(&self, priority: Priority, function: impl FnOnce() -> R) -> R {
+        let previous = self.priority.replace(priority);
+        let result = function();
+        self.priority.set(previous);
+        result
+    }
+
+    /// Limits the time in milliseconds `start` runs the tasks for. Once it is exceeded, the
+    /// remaining tasks, except the ones handling user input, are run after yielding to the
+    /// browser. By default all the tasks are run at once.
+    ///
+    /// Outside of the browser the remaining tasks wait until `start` is called again.
+    pub fn set_time_budget(&self, budget: Option<f64>) {
+        self.budget.set(budget);
+    }
+
+    /// Batches the renders of the components until the next animation frame, instead of
+    /// running them as soon as the components are updated. Updates are still handled right
+    /// away, but a component is rendered at most once per frame, so its `rendered` method is
+    /// called once per frame too. When the batching is disabled, the pending renders are run.
+    ///
+    /// Outside of the browser there are no animation frames, and the renders wait until
+    /// [`flush_frame`](Self::flush_frame) is called.
+    pub fn set_frame_batching(&self, enabled: bool) {
+        self.frame.enabled.set(enabled);
+        if !enabled {
+            self.flush_frame();
+        }
+    }
+
+    /// Schedules the render of the component identified by `key`. When the renders are
+    /// batched, it is deferred to the next frame, unless the component wasn't rendered in
+    /// the frame which is running.
+    pub(crate) fn push_render(&self, key: usize, runnable: Box<dyn Runnable>) {
+        let frame = &self.frame;
+        if !frame.enabled.get()
+            || (frame.rendering.get() && frame.rendered.borrow_mut().insert(key))
+        {
+            self.push_comp(ComponentRunnableType::Render, runnable);
+            return;
+        }
+
+        let mut pending = frame.pending.borrow_mut();
+        // The render which is already pending renders the latest view of the component
+        if pending.iter().all(|pending| pending.key != key) {
+            pending.push(PendingRender {
+                key,
+                priority: self.priority(),
+                runnable: self.record(RunnableKind::Render, runnable),
+            });
+        }
+        if !frame.requested.replace(true) {
+            request_frame();
+        }
+    }
+
+    /// Runs the renders which wait for the next frame.
+    pub fn flush_frame(&self) {
+        self.frame.requested.set(false);
+        let pending = std::mem::take(&mut *self.frame.pending.borrow_mut());
+        if pending.is_empty() {
+            return;
+        }
+
+        self.frame.rendering.set(true);
+        {
+            let mut rendered = self.frame.rendered.borrow_mut();
+            for render in pending {
+                rendered.insert(render.key);
+                let lane = self.lane(render.priority);
+                lane.render.borrow_mut().push(render.runnable);
+            }
+        }
+        self.start();
+        self.frame.rendering.set(false);
+        self.frame.rendered.borrow_mut().clear();
+    }
+
+    fn lane(&self, priority: Priority) -> &Lane {
+        &self.lanes[priority.index()]
+    }
+
     pub fn push_comp(&self, run_type: ComponentRunnableType, runnable: Box<dyn Runnable>) {
+        self.push_comp_with_priority(run_type, runnable, self.priority());
+    }
+
+    /// Pushes a component task with a `priority` instead of the current one.
+    pub fn push_comp_with_priority(
+        &self,
+        run_type: ComponentRunnableType,
+        runnable: Box<dyn Runnable>,
+        priority: Priority,
+    ) {
+        let lane = self.lane(priority);
+        let runnable = self.record(RunnableKind::from(&run_type), runnable);
         match run_type {
             ComponentRunnableType::Destroy => {
                 self.component.destroy.borrow_mut().push_back(runnable)
             }
             ComponentRunnableType::Create => self.component.create.borrow_mut().push_back(runnable),
-            ComponentRunnableType::Update => self.component.update.borrow_mut().push_back(runnable),
-            ComponentRunnableType::Render => self.component.render.borrow_mut().push(runnable),
+            ComponentRunnableType::Update => lane.update.borrow_mut().push_back(runnable),
+            ComponentRunnableType::Render => lane.render.borrow_mut().push(runnable),
         };
         self.start();
     }
 
     pub fn push(&self, runnable: Box<dyn Runnable>) {
-        self.main.borrow_mut().push_back(runnable);
+        self.push_with_priority(runnable, self.priority());
+    }
+
+    /// Pushes a task with a `priority` instead of the current one.
+    pub fn push_with_priority(&self, runnable: Box<dyn Runnable>, priority: Priority) {
+        let runnable = self.record(RunnableKind::Task, runnable);
+        self.lane(priority).main.borrow_mut().push_back(runnable);
         self.start();
     }
 
-    fn next_runnable(&self) -> Option<Box<dyn Runnable>> {
-        None.or_else(|| self.component.next_runnable())
-            .or_else(|| self.main.borrow_mut().pop_front())
+    /// Returns the priority of the next task, if any. The `UserInput` and `Normal` updates
+    /// come first, so that no component is rendered before they are handled. The `Idle`
+    /// updates wait for the `UserInput` renders, so that background work never delays the
+    /// feedback of user input.
+    fn next_priority(&self) -> Option<Priority> {
+        // The lanes in the order they run, with whether only their updates run at this point
+        const ORDER: [(Priority, bool); 6] = [
+            (Priority::UserInput, true),
+            (Priority::Normal, true),
+            (Priority::UserInput, false),
+            (Priority::Idle, true),
+            (Priority::Normal, false),
+            (Priority::Idle, false),
+        ];
+        ORDER
+            .iter()
+            .find(|(priority, updates)| {
+                let lane = self.lane(*priority);
+                if *updates {
+                    !lane.update.borrow().is_empty()
+                } else {
+                    !lane.is_empty()
+                }
+            })
+            .map(|(priority, _)| *priority)
     }
 
     pub fn start(&self) {
+        if !self.manual.get() {
+            self.run(None, true);
+        }
+    }
+
+    /// Runs at most `limit` tasks, yielding once the time budget is exceeded if `budgeted`,
+    /// and returns how many ran.
+    fn run(&self, limit: Option<usize>, budgeted: bool) -> usize {
+        let mut ran = 0;
         // The lock is used to prevent recursion. If the lock
         // cannot be acquired, it is because the `start()` method
         // is being called recursively as part of a `runnable.run()`.
         if let Ok(_lock) = self.lock.try_borrow_mut() {
-            while let Some(runnable) = self.next_runnable() {
-                runnable.run();
+            let started = now();
+            while limit.is_none_or(|limit| ran < limit) {
+                if let Some(runnable) = self.component.next_runnable() {
+                    runnable.run();
+                    ran += 1;
+                    continue;
+                }
+                let priority = match self.next_priority() {
+                    Some(priority) => priority,
+                    None => break,
+                };
+                let exceeded = budgeted
+                    && self
+                        .budget
+                        .get()
+                        .is_some_and(|budget| now() - started > budget);
+                if ran > 0 && exceeded && priority != Priority::UserInput {
+                    self.request_continuation();
+                    break;
+                }
+                if let Some(runnable) = self.lane(priority).next_runnable() {
+                    self.with_priority(priority, || runnable.run());
+                    ran += 1;
+                }
             }
         }
+        ran
+    }
+
+    /// Runs the remaining tasks after yielding to the browser.
+    fn request_continuation(&self) {
+        if !self.continuation_requested.replace(true) {
+            request_continuation();
+        }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(target_arch = "wasm32")]
+fn request_continuation() {
+    let callback = Closure::once_into_js(|| {
+        let scheduler = scheduler();
+        scheduler.continuation_requested.set(false);
+        scheduler.start();
+    });
+    crate::utils::window()
+        .set_timeout_with_callback(callback.unchecked_ref())
+        .expect("can't schedule the remaining tasks");
+}
+
+/// Outside of the browser the remaining tasks run when `start` is called again.
+#[cfg(not(target_arch = "wasm32"))]
+fn request_continuation() {}
+
+#[cfg(target_arch = "wasm32")]
+fn request_frame() {
+    let callback = Closure::once_into_js(|| scheduler().flush_frame());
+    crate::utils::window()
+        .request_animation_frame(callback.unchecked_ref())
+        .expect("can't request an animation frame");
+}
+
+/// There are no animation frames outside of the browser.
+#[cfg(not(target_arch = "wasm32"))]
+fn request_frame() {}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    thread_local! {
+        static LOG: RefCell<Vec<&'static str>> = RefCell::new(Vec::new());
+    }
+
+    fn take_log() -> Vec<&'static str> {
+        LOG.with(|log| std::mem::take(&mut *log.borrow_mut()))
+    }
+
+    /// A task which logs its name, after sleeping for `delay` milliseconds.
+    struct Task {
+        name: &'static str,
+        delay: u64,
+    }
+
+    fn task(name: &'static str) -> Box<dyn Runnable> {
+        Box::new(Task { name, delay: 0 })
+    }
+
+    fn slow_task(name: &'static str) -> Box<dyn Runnable> {
+        Box::new(Task { name, delay: 2 })
+    }
+
+    impl Runnable for Task {
+        fn run(self: Box<Self>) {
+            std::thread::sleep(Duration::from_millis(self.delay));
+            LOG.with(|log| log.borrow_mut().push(self.name));
+        }
+    }
+
+    #[test]
+    fn tasks_run_by_priority() {
+        let scheduler = scheduler();
+        scheduler.set_manual(true);
+        scheduler.push_with_priority(task("idle"), Priority::Idle);
+        scheduler.push_with_priority(task("normal"), Priority::Normal);
+        scheduler.push_with_priority(task("input"), Priority::UserInput);
+        scheduler.run_until_idle();
+        scheduler.set_manual(false);
+
+        assert_eq!(take_log(), vec!["input", "normal", "idle"]);
+    }
+
+    #[test]
+    fn updates_run_before_renders() {
+        let scheduler = scheduler();
+        scheduler.set_manual(true);
+        let (render, update) = (ComponentRunnableType::Render, ComponentRunnableType::Update);
+        scheduler.push_comp_with_priority(render, task("render"), Priority::UserInput);
+        scheduler.push_with_priority(task("task"), Priority::Normal);
+        scheduler.push_comp_with_priority(update, task("idle update"), Priority::Idle);
+        let update = ComponentRunnableType::Update;
+        scheduler.push_comp_with_priority(update, task("update"), Priority::Normal);
+        scheduler.run_until_idle();
+        scheduler.set_manual(false);
+
+        assert_eq!(take_log(), vec!["update", "render", "idle update", "task"]);
+    }
+
+    #[test]
+    fn idle_updates_wait_for_user_input_renders() {
+        let scheduler = scheduler();
+        scheduler.set_manual(true);
+        let (render, update) = (ComponentRunnableType::Render, ComponentRunnableType::Update);
+        scheduler.push_comp_with_priority(update, task("idle update"), Priority::Idle);
+        scheduler.push_comp_with_priority(render, task("idle render"), Priority::Idle);
+        let (render, update) = (ComponentRunnableType::Render, ComponentRunnableType::Update);
+        scheduler.push_comp_with_priority(update, task("input update"), Priority::UserInput);
+        scheduler.push_comp_with_priority(render, task("input render"), Priority::UserInput);
+        scheduler.run_until_idle();
+        scheduler.set_manual(false);
+
+        assert_eq!(
+            take_log(),
+            vec!["input update", "input render", "idle update", "idle render"]
+        );
+    }
+
+    #[test]
+    fn budget_yields_until_start_is_called_again() {
+        let scheduler = scheduler();
+        scheduler.set_manual(true);
+        for name in &["first", "second", "third"] {
+            scheduler.push_with_priority(slow_task(name), Priority::Normal);
+        }
+        scheduler.set_manual(false);
+        scheduler.set_time_budget(Some(1.0));
+
+        scheduler.start();
+        assert_eq!(take_log(), vec!["first"]);
+        assert_eq!(scheduler.pending_count(), 2);
+        scheduler.start();
+        assert_eq!(take_log(), vec!["second"]);
+
+        scheduler.set_time_budget(None);
+        scheduler.start();
+        assert_eq!(take_log(), vec!["third"]);
+    }
+
+    #[test]
+    fn budget_doesnt_delay_user_input() {
+        let scheduler = scheduler();
+        scheduler.set_manual(true);
+        scheduler.push_with_priority(slow_task("normal"), Priority::Normal);
+        for name in &["first", "second"] {
+            scheduler.push_with_priority(slow_task(name), Priority::UserInput);
+        }
+        scheduler.set_manual(false);
+        scheduler.set_time_budget(Some(1.0));
+
+        scheduler.start();
+        assert_eq!(take_log(), vec!["first", "second"]);
+        assert_eq!(scheduler.pending_count(), 1);
+
+        scheduler.set_time_budget(None);
+        scheduler.start();
+        assert_eq!(take_log(), vec!["normal"]);
+    }
+}
@@ -0,0 +1,400 @@
+//! Utilities to test components natively, without a browser.
+//!
+//! Components are mounted to the in-memory DOM of the [`MemoryBackend`], so their rendered
+//! nodes can be queried and synthetic events can be dispatched to the listeners of the nodes.
+//!
+//! ```
+//! # use djed::djed::listener::onclick;
+//! # use djed::djed::{Component, ComponentLink, Html};
+//! # use djed::djed_dom::{VTag, VText};
+//! # use djed::testing::TestRenderer;
+//! # use std::rc::Rc;
+//! #
+//! # struct Counter {
+//! #     link: ComponentLink<Self>,
+//! #     count: usize,
+//! # }
+//! #
+//! # impl Component for Counter {
+//! #     type State = ();
+//! #     type Props = ();
+//! #
+//! #     fn create(_: (), link: ComponentLink<Self>) -> Self {
+//! #         Counter { link, count: 0 }
+//! #     }
+//! #
+//! #     fn update(&mut self, _: ()) -> bool {
+//! #         self.count += 1;
+//! #         true
+//! #     }
+//! #
+//! #     fn change(&mut self, _: ()) -> bool {
+//! #         false
+//! #     }
+//! #
+//! #     fn view(&self) -> Html {
+//! #         let mut count = VTag::new("span");
+//! #         count.add_attribute("id", &"count");
+//! #         count.add_child(VText::new(self.count.to_string()).into());
+//! #         let mut button = VTag::new("button");
+//! #         button.add_listener(Rc::new(onclick::Wrapper::new(self.link.callback(|_| ()))));
+//! #         let mut counter = VTag::new("div");
+//! #         counter.add_child(count.into());
+//! #         counter.add_child(button.into());
+//! #         counter.into()
+//! #     }
+//! # }
+//! #
+//! let renderer = TestRenderer::<Counter>::mount();
+//! let button = renderer.find_by_tag("button").remove(0);
+//! renderer.click(&button);
+//! assert_eq!(renderer.find_by_id("count").unwrap().text_content(), "1");
+//! ```
+//!
+//! The synthetic events wrap `undefined`, so listeners which call the methods of their event
+//! panic natively. Their data is read through the backend instead, like their key with
+//! `Backend::event_key`, see [`MemoryBackend`].
+//!
+//! Timeouts only run when the test advances the time with [`TestRenderer::advance_time`],
+//! and spawned futures are polled whenever the renderer is flushed.
+//!
+//! The rendered output can also be compared against snapshots stored on disk, see the
+//! [`snapshot`] module.
+
+pub mod snapshot;
+
+use crate::djed::{Component, ComponentLink};
+use crate::djed_dom::backend::{DomBackend, MemoryBackend, MemoryNode};
+use crate::djed_dom::Render;
+use crate::scheduler::scheduler;
+use std::fmt;
+use std::time::Duration;
+
+/// A component mounted to an in-memory root element.
+pub struct TestRenderer<COMP: Component> {
+    root: MemoryNode,
+    link: ComponentLink<COMP>,
+}
+
+impl<COMP: Component> fmt::Debug for TestRenderer<COMP> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TestRenderer<_>")
+    }
+}
+
+impl<COMP> TestRenderer<COMP>
+where
+    COMP: Component,
+    COMP::Props: Default,
+{
+    /// Mounts the component with default properties.
+    pub fn mount() -> Self {
+        Self::mount_with_props(COMP::Props::default())
+    }
+
+    /// Mounts the component with default properties and its events delegated to the root
+    /// element, see [`Render::with_event_delegation`].
+    pub fn mount_with_event_delegation() -> Self {
+        Self::mount_render(
+            Render::new().with_event_delegation(),
+            COMP::Props::default(),
+        )
+    }
+}
+
+impl<COMP> TestRenderer<COMP>
+where
+    COMP: Component,
+{
+    /// Mounts the component with properties and runs the scheduled lifecycle methods.
+    pub fn mount_with_props(props: COMP::Props) -> Self {
+        Self::mount_render(Render::new(), props)
+    }
+
+    fn mount_render(render: Render<COMP>, props: COMP::Props) -> Self {
+        let root = MemoryNode::new_element("body");
+        let link = render.mount_with_props(root.clone(), props);
+        let renderer = TestRenderer { root, link };
+        renderer.flush();
+        renderer
+    }
+
+    /// Returns the root element the component is mounted to.
+    pub fn root(&self) -> &MemoryNode {
+        &self.root
+    }
+
+    /// Returns the link of the mounted component.
+    pub fn link(&self) -> &ComponentLink<COMP> {
+        &self.link
+    }
+
+    /// Returns the markup rendered by the component.
+    pub fn html(&self) -> String {
+        self.root.inner_html()
+    }
+
+    /// Returns the rendered tree serialized to a stable, pretty-printed text.
+    /// See [`snapshot::to_snapshot`].
+    pub fn snapshot(&self) -> String {
+        snapshot::to_snapshot(&self.root)
+    }
+
+    /// Compares the rendered tree with the snapshot stored under `name`.
+    /// See [`snapshot::assert_snapshot`].
+    pub fn assert_snapshot(&self, name: &str) {
+        snapshot::assert_snapshot(name, &self.root);
+    }
+
+    /// Polls the spawned futures and runs all the tasks waiting in the scheduler, so that
+    /// messages sent to components are handled and the resulting renders are applied,
+    /// including the ones batched until the next frame.
+    pub fn flush(&self) {
+        MemoryBackend::run_futures();
+        let scheduler = scheduler();
+        scheduler.run_until_idle();
+        scheduler.flush_frame();
+        scheduler.run_until_idle();
+    }
+
+    /// Advances the time of the in-memory backend by `duration`, so that the timeouts which
+    /// are due are called, and flushes the scheduler.
+    pub fn advance_time(&self, duration: Duration) {
+        MemoryBackend::advance_time(duration);
+        self.flush();
+    }
+
+    /// Returns all the elements, in document order, which match the `predicate`.
+    pub fn find_all(&self, predicate: impl Fn(&MemoryNode) -> bool) -> Vec<MemoryNode> {
+        let mut found = Vec::new();
+        collect_elements(&self.root, &predicate, &mut found);
+        found
+    }
+
+    /// Returns all the elements with the `tag`.
+    pub fn find_by_tag(&self, tag: &str) -> Vec<MemoryNode> {
+        self.find_all(|node| {
+            node.tag_name()
+                .is_some_and(|name| name.eq_ignore_ascii_case(tag))
+        })
+    }
+
+    /// Returns the first element with the `id`.
+    pub fn find_by_id(&self, id: &str) -> Option<MemoryNode> {
+        self.find_all(|node| node.attribute("id").as_deref() == Some(id))
+            .into_iter()
+            .next()
+    }
+
+    /// Returns all the elements which have the `class`.
+    pub fn find_by_class(&self, class: &str) -> Vec<MemoryNode> {
+        self.find_all(|node| {
+            node.attribute("class")
+                .is_some_and(|classes| classes.split_whitespace().any(|c| c == class))
+        })
+    }
+
+    /// Returns all the elements which have a text node with the `text` as a child.
+    pub fn find_by_text(&self, text: &str) -> Vec<MemoryNode> {
+        self.find_all(|node| {
+            node.children()
+                .iter()
+                .any(|child| child.text().as_deref() == Some(text))
+        })
+    }
+
+    /// Dispatches a `click` event to the element and flushes the scheduler.
+    pub fn click(&self, element: &MemoryNode) {
+        self.dispatch(element, "click");
+    }
+
+    /// Sets the `value` of the element, dispatches an `input` event to it and flushes the
+    /// scheduler.
+    pub fn input(&self, element: &MemoryNode, value: &str) {
+        MemoryBackend::set_value(element, value);
+        self.dispatch(element, "input");
+    }
+
+    /// Sets the `value` of the element, dispatches a `change` event to it and flushes the
+    /// scheduler.
+    pub fn change(&self, element: &MemoryNode, value: &str) {
+        MemoryBackend::set_value(element, value);
+        self.dispatch(element, "change");
+    }
+
+    /// Selects the options of a `<select>` whose value is one of `values`, dispatches a
+    /// `change` event to it and flushes the scheduler.
+    pub fn select(&self, element: &MemoryNode, values: &[&str]) {
+        let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+        MemoryBackend::set_selected_values(element, &values);
+        self.dispatch(element, "change");
+    }
+
+    /// Dispatches a `keydown` event of `key`, like `Enter`, to the element and flushes the
+    /// scheduler. Listeners read the key with `Backend::event_key`, since the methods of the
+    /// `KeyboardEvent` passed to them panic natively. Returns `false` if a listener prevented
+    /// the default action of the event.
+    pub fn keydown(&self, element: &MemoryNode, key: &str) -> bool {
+        let not_prevented = element.dispatch_keyboard_event("keydown", key);
+        self.flush();
+        not_prevented
+    }
+
+    /// Dispatches an event of any type to the element and flushes the scheduler. Returns
+    /// `false` if a listener prevented the default action of the event.
+    pub fn dispatch(&self, element: &MemoryNode, event_type: &str) -> bool {
+        let not_prevented = element.dispatch_event(event_type);
+        self.flush();
+        not_prevented
+    }
+}
+
+fn collect_elements(
+    node: &MemoryNode,
+    predicate: &impl Fn(&MemoryNode) -> bool,
+    found: &mut Vec<MemoryNode>,
+) {
+    for child in node.children().iter().filter(|child| child.is_element()) {
+        if predicate(child) {
+            found.push(child.clone());
+        }
+        collect_elements(child, predicate, found);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::djed::listener::{onclick, oninput, onkeydown, InputData};
+    use crate::djed::Html;
+    use crate::djed_dom::backend::Backend;
+    use crate::djed_dom::{VTag, VText};
+    use std::rc::Rc;
+    use web_sys::KeyboardEvent;
+
+    enum Msg {
+        Increment,
+        Rename(String),
+    }
+
+    struct Counter {
+        link: ComponentLink<Self>,
+        count: usize,
+        name: String,
+    }
+
+    impl Component for Counter {
+        type State = Msg;
+        type Props = ();
+
+        fn create(_: (), link: ComponentLink<Self>) -> Self {
+            Counter {
+                link,
+                count: 0,
+                name: String::new(),
+            }
+        }
+
+        fn update(&mut self, msg: Msg) -> bool {
+            match msg {
+                Msg::Increment => self.count += 1,
+                Msg::Rename(name) => self.name = name,
+            }
+            true
+        }
+
+        fn change(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn view(&self) -> Html {
+            let mut name = VTag::new("input");
+            let rename = self.link.callback(|data: InputData| Msg::Rename(data.value));
+            name.add_listener(Rc::new(oninput::Wrapper::new(rename)));
+            let submit = self.link.batch_callback(|event: KeyboardEvent| {
+                match Backend::event_key(&event).as_str() {
+                    "Enter" => vec![Msg::Increment],
+                    _ => Vec::new(),
+                }
+            });
+            name.add_listener(Rc::new(onkeydown::Wrapper::new(submit)));
+            let mut count = VTag::new("span");
+            count.add_attribute("id", &"count");
+            count.add_attribute("class", &"count large");
+            count.add_child(VText::new(format!("{} {}", self.name, self.count)).into());
+            let mut increment = VTag::new("button");
+            let click = self.link.callback(|_| Msg::Increment);
+            increment.add_listener(Rc::new(onclick::Wrapper::new(click).prevent_default()));
+            increment.add_child(VText::new("+".to_string()).into());
+            let mut counter = VTag::new("div");
+            counter.add_child(name.into());
+            counter.add_child(count.into());
+            counter.add_child(increment.into());
+            counter.into()
+        }
+    }
+
+    fn count(renderer: &TestRenderer<Counter>) -> String {
+        renderer.find_by_id("count").unwrap().text_content()
+    }
+
+    #[test]
+    fn click_updates_the_text() {
+        let renderer = TestRenderer::<Counter>::mount();
+        assert_eq!(count(&renderer), " 0");
+
+        let button = renderer.find_by_tag("button").remove(0);
+        renderer.click(&button);
+        renderer.click(&button);
+        assert_eq!(count(&renderer), " 2");
+    }
+
+    #[test]
+    fn input_updates_the_text() {
+        let renderer = TestRenderer::<Counter>::mount();
+        let input = renderer.find_by_tag("input").remove(0);
+        renderer.input(&input, "clicks");
+        assert_eq!(count(&renderer), "clicks 0");
+    }
+
+    #[test]
+    fn keydown_passes_its_key() {
+        let renderer = TestRenderer::<Counter>::mount();
+        let input = renderer.find_by_tag("input").remove(0);
+        renderer.keydown(&input, "a");
+        assert_eq!(count(&renderer), " 0");
+        renderer.keydown(&input, "Enter");
+        assert_eq!(count(&renderer), " 1");
+    }
+
+    #[test]
+    fn delegated_keydown_passes_its_key() {
+        let renderer = TestRenderer::<Counter>::mount_with_event_delegation();
+        let input = renderer.find_by_tag("input").remove(0);
+        renderer.keydown(&input, "Escape");
+        renderer.keydown(&input, "Enter");
+        assert_eq!(count(&renderer), " 1");
+    }
+
+    #[test]
+    fn dispatch_reports_prevented_default() {
+        let renderer = TestRenderer::<Counter>::mount();
+        let button = renderer.find_by_tag("button").remove(0);
+        assert!(!renderer.dispatch(&button, "click"));
+        assert!(renderer.dispatch(&button, "mouseover"));
+    }
+
+    #[test]
+    fn find_elements() {
+        let renderer = TestRenderer::<Counter>::mount();
+        assert_eq!(renderer.find_by_class("large").len(), 1);
+        assert_eq!(renderer.find_by_class("arg").len(), 0);
+        assert_eq!(renderer.find_by_text("+").len(), 1);
+        assert!(renderer.find_by_id("missing").is_none());
+        assert_eq!(
+            renderer.html(),
+            "<div><input></input><span class=\"count large\" id=\"count\"> 0</span>\
+             <button>+</button></div>"
+        );
+    }
+}
@@ -0,0 +1,334 @@
+//! Snapshot assertions on rendered trees.
+//!
+//! A rendered tree is serialized to an HTML-like text with one node per line. Attributes and
+//! classes are sorted, the `value`, `checked` and `selected` properties of form elements and
+//! the properties set with [`VTag::set_property`](crate::djed_dom::VTag::set_property) are
+//! shown with a leading `.` and the attached listeners are shown by event name with a leading `@`:
+//!
+//! ```text
+//! <div class="counter highlighted" id="count">
+//!   <button @click>
+//!     +1
+//!   </button>
+//!   <input type="text" .value="hello" @input />
+//! </div>
+//! ```
+//!
+//! The text nodes are written one trimmed line at a time and their blank lines are left out,
+//! so snapshots don't change with the whitespace of the rendered text. Elements without
+//! other children than blank text nodes are written as self-closing tags.
+//!
+//! Snapshots are stored in `tests/snapshots/<name>.snap` of the crate being tested. When the
+//! `DJED_UPDATE_SNAPSHOTS` environment variable is set, assertions write the rendered tree to
+//! the snapshot instead of comparing them, so that changes can be reviewed as diffs.
+
+use crate::djed_dom::backend::MemoryNode;
+use crate::djed_dom::{escape_attribute, escape_text};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// The environment variable which makes assertions update their snapshot.
+pub const UPDATE_SNAPSHOTS_VAR: &str = "DJED_UPDATE_SNAPSHOTS";
+
+const INDENT: &str = "  ";
+
+/// Serializes the children of `root` to a stable, pretty-printed text.
+pub fn to_snapshot(root: &MemoryNode) -> String {
+    let mut snapshot = String::new();
+    for child in root.children() {
+        write_node(&child, 0, &mut snapshot);
+    }
+    snapshot
+}
+
+/// Compares the children of `root` with the snapshot stored under `name`.
+///
+/// # Panics
+/// Panics with a line diff if the tree doesn't match the snapshot, or if there is no
+/// snapshot yet, unless `DJED_UPDATE_SNAPSHOTS` is set.
+pub fn assert_snapshot(name: &str, root: &MemoryNode) {
+    let actual = to_snapshot(root);
+    let path = snapshot_path(name);
+
+    if update_snapshots() {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).expect("can't create the snapshot directory");
+        }
+        fs::write(&path, &actual).expect("can't write the snapshot");
+        return;
+    }
+
+    let expected = match fs::read_to_string(&path) {
+        Ok(expected) => expected.replace("\r\n", "\n"),
+        Err(_) => panic!(
+            "snapshot `{}` doesn't exist at {}, run the test with {}=1 to create it:\n{}",
+            name,
+            path.display(),
+            UPDATE_SNAPSHOTS_VAR,
+            actual
+        ),
+    };
+
+    if expected != actual {
+        panic!(
+            "snapshot `{}` doesn't match the rendered tree, run the test with {}=1 to \
+             update it:\n{}",
+            name,
+            UPDATE_SNAPSHOTS_VAR,
+            diff_lines(&expected, &actual)
+        );
+    }
+}
+
+fn update_snapshots() -> bool {
+    env::var(UPDATE_SNAPSHOTS_VAR)
+        .map(|value| !value.is_empty() && value != "0")
+        .unwrap_or(false)
+}
+
+fn snapshot_path(name: &str) -> PathBuf {
+    let mut path = env::var_os("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_default();
+    path.push("tests");
+    path.push("snapshots");
+    path.push(format!("{}.snap", name));
+    path
+}
+
+fn write_node(node: &MemoryNode, depth: usize, snapshot: &mut String) {
+    if let Some(text) = node.text() {
+        // Empty text nodes are placeholders of empty lists
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            write_indent(depth, snapshot);
+            escape_text(line.trim(), snapshot);
+            snapshot.push('\n');
+        }
+        return;
+    }
+
+    let tag = node.tag_name().unwrap_or_default();
+    write_indent(depth, snapshot);
+    snapshot.push('<');
+    snapshot.push_str(&tag);
+    for (name, value) in node.attributes() {
+        let value = if name == "class" {
+            let mut classes: Vec<&str> = value.split_whitespace().collect();
+            classes.sort_unstable();
+            classes.dedup();
+            classes.join(" ")
+        } else {
+            value
+        };
+        snapshot.push(' ');
+        snapshot.push_str(&name);
+        snapshot.push_str("=\"");
+        escape_attribute(&value, snapshot);
+        snapshot.push('"');
+    }
+    if matches!(tag.as_str(), "input" | "textarea") {
+        let value = node.value().unwrap_or_default();
+        if !value.is_empty() {
+            snapshot.push_str(" .value=\"");
+            escape_attribute(&value, snapshot);
+            snapshot.push('"');
+        }
+    }
+    if node.checked() {
+        snapshot.push_str(" .checked");
+    }
+    if node.selected() {
+        snapshot.push_str(" .selected");
+    }
+    for (name, value) in node.properties() {
+        snapshot.push_str(" .");
+        snapshot.push_str(&name);
+        snapshot.push('=');
+        snapshot.push_str(&value.to_string());
+    }
+    let mut listeners = node.listeners();
+    listeners.sort_unstable();
+    listeners.dedup();
+    for listener in listeners {
+        snapshot.push_str(" @");
+        snapshot.push_str(listener);
+    }
+
+    // The markup of raw nodes isn't parsed by the in-memory DOM
+    if let Some(raw_html) = node.raw_html().filter(|raw_html| !raw_html.is_empty()) {
+        snapshot.push_str(">\n");
+        for line in raw_html.lines().filter(|line| !line.trim().is_empty()) {
+            write_indent(depth + 1, snapshot);
+            snapshot.push_str(line.trim());
+            snapshot.push('\n');
+        }
+        write_indent(depth, snapshot);
+        snapshot.push_str("</");
+        snapshot.push_str(&tag);
+        snapshot.push_str(">\n");
+        return;
+    }
+
+    let children = node.children();
+    if children
+        .iter()
+        .all(|child| child.text().is_some_and(|text| text.trim().is_empty()))
+    {
+        snapshot.push_str(" />\n");
+        return;
+    }
+
+    snapshot.push_str(">\n");
+    for child in children {
+        write_node(&child, depth + 1, snapshot);
+    }
+    write_indent(depth, snapshot);
+    snapshot.push_str("</");
+    snapshot.push_str(&tag);
+    snapshot.push_str(">\n");
+}
+
+fn write_indent(depth: usize, snapshot: &mut String) {
+    for _ in 0..depth {
+        snapshot.push_str(INDENT);
+    }
+}
+
+/// Returns the lines of both texts prefixed with `-` if they were only expected, `+` if they
+/// were only rendered, or a space if they are in both.
+fn diff_lines(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+
+    // Lengths of the longest common subsequences of the suffixes
+    let mut lengths = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            lengths[i][j] = if expected[i] == actual[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        let (prefix, line) = if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            i += 1;
+            j += 1;
+            (' ', actual[j - 1])
+        } else if j < actual.len()
+            && (i == expected.len() || lengths[i][j + 1] >= lengths[i + 1][j])
+        {
+            j += 1;
+            ('+', actual[j - 1])
+        } else {
+            i += 1;
+            ('-', expected[i - 1])
+        };
+        diff.push(prefix);
+        diff.push_str(line);
+        diff.push('\n');
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::djed_dom::backend::{DomBackend, MemoryBackend, MemoryListenerHandle};
+    use crate::djed_dom::ListenerOptions;
+
+    fn element(parent: &MemoryNode, tag: &str) -> MemoryNode {
+        let element = MemoryBackend::create_element(tag, None);
+        MemoryBackend::insert_before(parent, &element, None);
+        element
+    }
+
+    fn text(parent: &MemoryNode, text: &str) {
+        MemoryBackend::insert_before(parent, &MemoryBackend::create_text(text), None);
+    }
+
+    fn listen(element: &MemoryNode, event_type: &'static str) -> MemoryListenerHandle {
+        let options = ListenerOptions::default().to_event_listener_options(event_type);
+        MemoryBackend::add_listener(element, event_type, options, Box::new(|_| {}))
+    }
+
+    #[test]
+    fn sorts_attributes_and_classes() {
+        let root = MemoryNode::new_element("body");
+        let div = element(&root, "div");
+        MemoryBackend::set_attribute(&div, "id", "count");
+        MemoryBackend::set_attribute(&div, "class", "highlighted counter highlighted");
+        MemoryBackend::set_attribute(&div, "data-x", "a \"b\"");
+        text(&div, "1");
+
+        assert_eq!(
+            to_snapshot(&root),
+            "<div class=\"counter highlighted\" data-x=\"a &quot;b&quot;\" id=\"count\">\n  \
+             1\n</div>\n"
+        );
+    }
+
+    #[test]
+    fn writes_properties_and_listeners() {
+        let root = MemoryNode::new_element("body");
+        let input = element(&root, "input");
+        MemoryBackend::set_attribute(&input, "type", "text");
+        MemoryBackend::set_value(&input, "hello");
+        let _input = listen(&input, "input");
+        let _focus = listen(&input, "focus");
+        let _focus_again = listen(&input, "focus");
+        let check = element(&root, "input");
+        MemoryBackend::set_checked(&check, true);
+        let video = element(&root, "video");
+        MemoryBackend::set_property(&video, "muted", &true.into());
+
+        assert_eq!(
+            to_snapshot(&root),
+            "<input type=\"text\" .value=\"hello\" @focus @input />\n\
+             <input .checked />\n\
+             <video .muted=true />\n"
+        );
+    }
+
+    #[test]
+    fn self_closes_elements_with_blank_text() {
+        let root = MemoryNode::new_element("body");
+        element(&root, "br");
+        let list = element(&root, "ul");
+        text(&list, "");
+        text(&list, " \n ");
+        let item = element(&root, "li");
+        text(&item, "");
+        element(&item, "span");
+
+        assert_eq!(
+            to_snapshot(&root),
+            "<br />\n<ul />\n<li>\n  <span />\n</li>\n"
+        );
+    }
+
+    #[test]
+    fn trims_text_lines() {
+        let root = MemoryNode::new_element("body");
+        let p = element(&root, "p");
+        text(&p, "  first line  \n\n   second <line>\n");
+
+        assert_eq!(
+            to_snapshot(&root),
+            "<p>\n  first line\n  second &lt;line&gt;\n</p>\n"
+        );
+    }
+
+    #[test]
+    fn diff_marks_changed_lines() {
+        assert_eq!(diff_lines("a\nb\nc\n", "a\nb\nc\n"), " a\n b\n c\n");
+        assert_eq!(diff_lines("a\nb\nc", "a\nx\nc\nd"), " a\n+x\n-b\n c\n+d\n");
+        assert_eq!(diff_lines("", "a"), "+a\n");
+        assert_eq!(diff_lines("a", ""), "-a\n");
+    }
+}